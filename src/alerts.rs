@@ -0,0 +1,157 @@
+use chrono::{DateTime, Local};
+use std::{io, thread, time::Duration};
+use tracing::{info, warn};
+
+use crate::config::{self, EmailSettings};
+use crate::runs::{self, RunRecord};
+use crate::smtp;
+use crate::storage::backend::open_destination;
+use crate::webhooks;
+
+/// How often the watchdog looks at the run history and the destination.
+const CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Email a failed or skipped run. Sent in the background, so a slow mail server
+/// never holds up the next backup.
+pub fn on_run(settings: &EmailSettings, run: &RunRecord) {
+    if settings.smtp.host.is_empty() || settings.mute_failure || run.error.is_none() {
+        return;
+    }
+    let subject = if run.skipped {
+        "RustCraft - Backup skipped"
+    } else {
+        "RustCraft - Backup failed"
+    };
+    let mut body = format!("{}\n\n", webhooks::message(run));
+    for cause in run.error_chain.iter().skip(1) {
+        body.push_str(&format!("Caused by: {}\n", cause));
+    }
+    body.push_str(&format!(
+        "Source: {}\nDestination: {}\nStarted: {}\n",
+        run.source,
+        run.destination,
+        run.started.format("%d.%m.%Y %H:%M:%S")
+    ));
+    send_in_background(settings, subject, body);
+}
+
+/// Send a test email, to check the mail server settings.
+pub fn send_test(settings: &EmailSettings) -> io::Result<()> {
+    smtp::send_mail(
+        &settings.smtp,
+        "RustCraft - Test email",
+        "Email alerts from RustCraft reach you.",
+    )
+}
+
+/// Keep checking, from when the app starts and for as long as it runs, that backups still
+/// succeed and that the destination has room for them. Each alert is sent once, and again
+/// only after the problem went away and came back.
+pub fn start_watchdog() {
+    let mut watchdog = Watchdog {
+        started: Local::now(),
+        missed_sent: false,
+        low_space_sent: false,
+    };
+    thread::spawn(move || loop {
+        // Read again every time, so changed settings apply without a restart
+        watchdog.check(&config::load_configuration());
+        thread::sleep(CHECK_INTERVAL);
+    });
+}
+
+/// What the watchdog remembers between checks.
+struct Watchdog {
+    started: DateTime<Local>,
+    missed_sent: bool,
+    low_space_sent: bool,
+}
+
+impl Watchdog {
+    fn check(&mut self, config: &config::Config) {
+        let settings = &config.email;
+        if settings.smtp.host.is_empty() {
+            return;
+        }
+
+        match missed_backup(settings, self.started) {
+            Some(body) if !self.missed_sent => {
+                send_in_background(settings, "RustCraft - No recent backup", body);
+                self.missed_sent = true;
+            }
+            Some(_) => {}
+            None => self.missed_sent = false,
+        }
+
+        match low_space(config) {
+            Ok(Some(body)) if !self.low_space_sent => {
+                send_in_background(settings, "RustCraft - Backup destination almost full", body);
+                self.low_space_sent = true;
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => self.low_space_sent = false,
+            Err(e) => warn!("Could not check free space in the destination: {}", e),
+        }
+    }
+}
+
+// Describe the missed backups, or `None` when the last success is recent enough.
+// Before the first run ever, the window counts from when the app started.
+fn missed_backup(settings: &EmailSettings, started: DateTime<Local>) -> Option<String> {
+    if settings.missed_backup_hours == 0 {
+        return None;
+    }
+    let last_success = runs::load()
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+        .find(|run| run.error.is_none())
+        .map(|run| run.finished);
+    let since = last_success.unwrap_or(started);
+    let hours = (Local::now() - since).num_hours();
+    if hours < settings.missed_backup_hours as i64 {
+        return None;
+    }
+    Some(match last_success {
+        Some(last) => format!(
+            "No backup has succeeded since {}, {} hours ago.",
+            last.format("%d.%m.%Y %H:%M"),
+            hours
+        ),
+        None => format!(
+            "No backup has succeeded in the {} hours since RustCraft started.",
+            hours
+        ),
+    })
+}
+
+// Describe the shortage, or `None` when there is enough space or it cannot be known.
+fn low_space(config: &config::Config) -> io::Result<Option<String>> {
+    let minimum = config.email.min_free_space_mb * 1024 * 1024;
+    if minimum == 0 {
+        return Ok(None);
+    }
+    let Some(backend) = open_destination(config) else {
+        return Ok(None);
+    };
+    let backend = backend?;
+    Ok(backend
+        .available_space()?
+        .filter(|available| *available < minimum)
+        .map(|available| {
+            format!(
+                "Only {} are left in {}, less than the {} MB alerts are set for.",
+                runs::format_size(available),
+                backend.describe(),
+                config.email.min_free_space_mb
+            )
+        }))
+}
+
+fn send_in_background(settings: &EmailSettings, subject: &'static str, body: String) {
+    let smtp = settings.smtp.clone();
+    thread::spawn(move || match smtp::send_mail(&smtp, subject, &body) {
+        Ok(()) => info!("Sent the \"{}\" email to {}", subject, smtp.to.join(", ")),
+        Err(e) => warn!("Could not send the \"{}\" email: {}", subject, e),
+    });
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, Local};
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::errors::{self, Cause};
+use crate::file_operations;
+use crate::nbt::{self, Endian};
+
+/// Folder inside a Bedrock world that holds its LevelDB database.
+pub const LEVELDB_FOLDER: &str = "db";
+
+/// Lock file LevelDB keeps open while the world is loaded. It is never backed up.
+pub const LEVELDB_LOCK: &str = "LOCK";
+
+/// What RustCraft shows about a Bedrock world.
+#[derive(Debug, Clone)]
+pub struct WorldInfo {
+    pub name: String,
+    pub game_mode: &'static str,
+    pub version: Option<String>,
+    pub last_played: Option<DateTime<Local>>,
+}
+
+impl WorldInfo {
+    /// One line summary, e.g. `My World (Survival, 1.21.2, 01.06.2024)`.
+    pub fn summary(&self) -> String {
+        let mut details = vec![self.game_mode.to_string()];
+        details.extend(self.version.clone());
+        details.extend(
+            self.last_played
+                .map(|time| time.format("%d.%m.%Y").to_string()),
+        );
+        format!("{} ({})", self.name, details.join(", "))
+    }
+}
+
+/// Every `minecraftWorlds` folder of the Bedrock installations RustCraft knows about,
+/// with a name for each.
+pub fn worlds_folders() -> Vec<(String, PathBuf)> {
+    let mut folders = Vec::new();
+    if cfg!(target_os = "windows") {
+        if let Some(local) = dirs::data_local_dir() {
+            folders.push((
+                "Worlds".to_string(),
+                local
+                    .join("Packages")
+                    .join("Microsoft.MinecraftUWP_8wekyb3d8bbwe")
+                    .join("LocalState")
+                    .join("games")
+                    .join("com.mojang")
+                    .join("minecraftWorlds"),
+            ));
+        }
+        // Newer releases keep worlds per Xbox account under Roaming
+        if let Some(users) =
+            dirs::data_dir().map(|data| data.join("Minecraft Bedrock").join("Users"))
+        {
+            for entry in fs::read_dir(users).into_iter().flatten().flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                folders.push((
+                    name,
+                    entry
+                        .path()
+                        .join("games")
+                        .join("com.mojang")
+                        .join("minecraftWorlds"),
+                ));
+            }
+        }
+    } else {
+        // mcpelauncher, the usual way to play Bedrock on Linux
+        if let Some(data) = dirs::data_dir() {
+            folders.push(("mcpelauncher".to_string(), data.join("mcpelauncher")));
+        }
+        if let Some(home) = dirs::home_dir() {
+            folders.push((
+                "mcpelauncher (Flatpak)".to_string(),
+                home.join(".var")
+                    .join("app")
+                    .join("io.mrarm.mcpelauncher")
+                    .join("data")
+                    .join("mcpelauncher"),
+            ));
+        }
+        for (_, folder) in &mut folders {
+            *folder = folder
+                .join("games")
+                .join("com.mojang")
+                .join("minecraftWorlds");
+        }
+    }
+    folders
+}
+
+/// A Bedrock world is a folder with a `level.dat` next to a LevelDB `db` folder.
+pub fn is_world(path: &Path) -> bool {
+    path.join("level.dat").is_file() && path.join(LEVELDB_FOLDER).is_dir()
+}
+
+/// Whether `path` is the LevelDB folder of a Bedrock world.
+pub fn is_leveldb(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == LEVELDB_FOLDER)
+        && path.parent().is_some_and(is_world)
+}
+
+/// Files that must not be copied out of a LevelDB folder.
+pub fn is_leveldb_lock(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == LEVELDB_LOCK)
+        && path.parent().is_some_and(is_leveldb)
+}
+
+/// Take LevelDB's lock on `db`, so the world cannot be opened while it is being copied.
+/// Fails if Minecraft has the world open, because its files would change under the copy.
+/// The lock is released when the returned file is dropped.
+pub fn lock_leveldb(db: &Path) -> io::Result<File> {
+    let world_name = db
+        .parent()
+        .map(|world| read_world(world).name)
+        .unwrap_or_default();
+    let in_use = || {
+        errors::known(
+            Cause::WorldOpen,
+            format!(
+                "The Bedrock world \"{}\" is open in Minecraft. Close it and try again",
+                world_name
+            ),
+        )
+    };
+
+    let lock = match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(db.join(LEVELDB_LOCK))
+    {
+        Ok(lock) => lock,
+        // Windows refuses to open a file Minecraft holds exclusively
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return Err(in_use()),
+        Err(e) => return Err(e),
+    };
+    if file_operations::try_lock_exclusive(&lock)? {
+        Ok(lock)
+    } else {
+        Err(in_use())
+    }
+}
+
+/// Read a world's name from `levelname.txt` and its details from `level.dat`. Anything
+/// that cannot be read is left out rather than failing, so damaged worlds still show up.
+pub fn read_world(path: &Path) -> WorldInfo {
+    let level = read_level_dat(&path.join("level.dat")).ok();
+    let field = |key: &str| level.as_ref().and_then(|level| level.get(key));
+
+    let name = fs::read_to_string(path.join("levelname.txt"))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .or_else(|| {
+            field("LevelName")
+                .and_then(|name| name.as_str())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        });
+    let game_mode = match field("GameType").and_then(|mode| mode.as_i64()) {
+        Some(0) => "Survival",
+        Some(1) => "Creative",
+        Some(2) => "Adventure",
+        Some(6) => "Spectator",
+        _ => "Unknown mode",
+    };
+    let version = field("lastOpenedWithVersion")
+        .and_then(|version| version.as_list())
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part.as_i64())
+                .map(|part| part.to_string())
+                .collect::<Vec<_>>()
+                .join(".")
+        })
+        .filter(|version| !version.is_empty());
+    let last_played = field("LastPlayed")
+        .and_then(|time| time.as_i64())
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|time| time.with_timezone(&Local));
+
+    WorldInfo {
+        name,
+        game_mode,
+        version,
+        last_played,
+    }
+}
+
+/// Every world in a `minecraftWorlds` folder, by name.
+pub fn list_worlds(worlds_folder: &Path) -> Vec<WorldInfo> {
+    let mut worlds: Vec<WorldInfo> = fs::read_dir(worlds_folder)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_world(path))
+        .map(|path| read_world(&path))
+        .collect();
+    worlds.sort_by(|a, b| a.name.cmp(&b.name));
+    worlds
+}
+
+/// Bedrock's `level.dat` is little-endian NBT behind an 8 byte header
+/// (storage version and payload length).
+fn read_level_dat(path: &Path) -> io::Result<nbt::Tag> {
+    let data = fs::read(path)?;
+    let payload = data
+        .get(8..)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "level.dat is truncated"))?;
+    Ok(nbt::parse(payload, Endian::Little)?.1)
+}
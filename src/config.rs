@@ -1,43 +1,259 @@
-use serde_json::{json, Value};
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub const CONFIG_FILE: &str = "config.json";
 
-pub fn save_configuration(
-    minecraft_dir: &Option<String>,
-    backup_dir: &Option<String>,
-    backup_frequency: i32,
-    dark_theme: bool,
-) -> io::Result<()> {
-    let data = json!({
-        "minecraft_directory": minecraft_dir,
-        "backup_directory": backup_dir,
-        "backup_frequency": backup_frequency,
-        "dark_theme": dark_theme
-    });
-    fs::write(CONFIG_FILE, serde_json::to_string_pretty(&data)?)
-}
-
-pub fn load_configuration() -> (Option<String>, Option<String>, i32, bool) {
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub minecraft_directory: Option<String>,
+    pub backup_directory: Option<String>,
+    /// Backup frequency in hours, 0 for a one-time backup.
+    pub backup_frequency: i32,
+    pub dark_theme: bool,
+    /// Number of snapshots to keep in the destination, 0 to keep all of them.
+    pub keep_snapshots: usize,
+    /// Check every region file before backing up. Damage found is recorded as warnings of
+    /// the run; the backup still happens, as one bad chunk should not stop every backup.
+    pub check_regions: bool,
+    /// Render a map preview of the world with every backup, instead of the first time
+    /// the backup is opened in the history.
+    pub render_previews: bool,
+    /// Where worlds are backed up from. `minecraft_directory` is used for `local` and `server`.
+    pub source: SourceKind,
+    pub sftp_source: SftpSettings,
+    pub rcon: RconSettings,
+    /// Where remote worlds are downloaded before being backed up. Defaults to the local data folder.
+    pub staging_directory: Option<String>,
+    /// Where backups are written. `backup_directory` is only used for `local`.
+    pub destination: DestinationKind,
+    pub s3: S3Settings,
+    pub sftp: SftpSettings,
+    pub webdav: WebDavSettings,
+    pub encryption: EncryptionSettings,
+    /// `error`, `warn`, `info`, `debug` or `trace`. Defaults to `info`.
+    pub log_level: String,
+    pub notifications: NotificationSettings,
+    /// Chat channels or other services told about every backup.
+    pub webhooks: Vec<WebhookSettings>,
+    pub email: EmailSettings,
+    pub hooks: HookSettings,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    #[default]
+    Local,
+    Sftp,
+    /// A running server's world folder, with saving paused over RCON during the backup.
+    Server,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DestinationKind {
+    #[default]
+    Local,
+    S3,
+    Sftp,
+    WebDav,
+}
+
+/// Settings for an S3-compatible bucket (AWS S3, Backblaze B2, MinIO...).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct S3Settings {
+    /// e.g. `http://localhost:9000` for MinIO. Defaults to AWS for the configured region.
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub bucket: String,
+    /// Folder inside the bucket that snapshots are written to.
+    pub prefix: String,
+    /// Falls back to `AWS_ACCESS_KEY_ID` when not set.
+    pub access_key_id: Option<String>,
+    /// Falls back to `AWS_SECRET_ACCESS_KEY` when not set.
+    pub secret_access_key: Option<String>,
+    /// Use `endpoint/bucket/key` URLs instead of `bucket.endpoint/key`. MinIO needs this.
+    pub path_style: bool,
+}
+
+/// Folder for files RustCraft keeps for itself, like downloaded worlds and the run history.
+pub fn data_directory() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("RustCraft")
+}
+
+pub fn save_configuration(config: &Config) -> io::Result<()> {
+    fs::write(CONFIG_FILE, serde_json::to_string_pretty(config)?)
+}
+
+pub fn load_configuration() -> Config {
     let path = Path::new(CONFIG_FILE);
-    let mut backup_frequency = 0; // Default backup frequency in hours
-    let mut dark_theme = false;
-    let (minecraft_dir, backup_dir) = if path.exists() {
+    if path.exists() {
         let data = fs::read_to_string(path).unwrap();
-        let json: Value = serde_json::from_str(&data).unwrap();
-        let minecraft_dir = json["minecraft_directory"].as_str().map(String::from);
-        let backup_dir = json["backup_directory"].as_str().map(String::from);
-        if let Some(freq) = json["backup_frequency"].as_i64() {
-            backup_frequency = freq as i32;
-        }
-        if let Some(dark) = json["dark_theme"].as_bool() {
-            dark_theme = dark;
-        }
-        (minecraft_dir, backup_dir)
+        serde_json::from_str(&data).unwrap()
     } else {
-        (None, None)
-    };
-    (minecraft_dir, backup_dir, backup_frequency, dark_theme)
+        Config::default()
+    }
+}
+
+/// Connection settings for an SSH server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SftpSettings {
+    pub host: String,
+    /// Defaults to 22.
+    pub port: Option<u16>,
+    pub username: String,
+    pub password: Option<String>,
+    /// Path to a private key. Takes precedence over the password; if neither is set,
+    /// the SSH agent is used.
+    pub private_key: Option<String>,
+    pub private_key_passphrase: Option<String>,
+    /// Directory on the server, e.g. `/srv/minecraft/world` for a source or
+    /// `/volume1/backups/minecraft` for a destination.
+    pub remote_path: String,
+    /// Accept servers that are not in `~/.ssh/known_hosts` yet.
+    pub trust_unknown_host: bool,
+}
+
+/// Settings for a WebDAV server such as Nextcloud or ownCloud.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebDavSettings {
+    /// Folder backups are written to, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/alex/Minecraft`.
+    pub url: String,
+    pub username: Option<String>,
+    /// For Nextcloud, use an app password rather than your login password.
+    pub password: Option<String>,
+}
+
+/// Client-side encryption of everything written to the destination.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncryptionSettings {
+    pub enabled: bool,
+    /// Falls back to `RUSTCRAFT_PASSPHRASE` when not set. If it is lost,
+    /// encrypted backups cannot be restored.
+    pub passphrase: Option<String>,
+}
+
+/// Which desktop notifications are shown. All of them are by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub mute_success: bool,
+    pub mute_failure: bool,
+}
+
+/// A URL that is posted a message after each backup.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    /// For Discord, the channel's webhook URL from its integration settings.
+    pub url: String,
+    pub format: WebhookFormat,
+    /// Body of `json` webhooks, with placeholders like `{{message}}`, `{{event}}` or
+    /// `{{snapshot_id}}` replaced by JSON strings. Defaults to the whole run record.
+    pub template: Option<String>,
+    pub mute_success: bool,
+    pub mute_failure: bool,
+    /// Backups that were refused on purpose, like when a pre-backup hook failed.
+    pub mute_skipped: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    Discord,
+    Slack,
+    #[default]
+    Json,
+}
+
+/// Emails sent when something needs attention. Nothing is sent until `smtp.host` is set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmailSettings {
+    pub smtp: SmtpSettings,
+    pub mute_failure: bool,
+    /// Send an email when no backup has succeeded for this many hours, 0 to never.
+    pub missed_backup_hours: u64,
+    /// Send an email when the destination has less free space than this many megabytes, 0 to never.
+    pub min_free_space_mb: u64,
+}
+
+/// Connection settings for a mail server.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Defaults to 465 for `tls`, 587 for `starttls` and 25 for `none`.
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// Leave empty for servers that accept mail without logging in.
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Address the emails are sent from, e.g. `rustcraft@example.com`.
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// TLS from the start, usually on port 465.
+    Tls,
+    /// Plain connection upgraded to TLS, usually on port 587.
+    #[default]
+    StartTls,
+    /// No encryption, only for servers on the same machine or network.
+    None,
+}
+
+/// Commands run around every backup, like stopping a server or rendering a map.
+///
+/// Hooks get `RUSTCRAFT_SOURCE` and `RUSTCRAFT_DESTINATION` in their environment. Post-backup
+/// hooks also get `RUSTCRAFT_RESULT` (`success`, `failure` or `skipped`), `RUSTCRAFT_ERROR`,
+/// `RUSTCRAFT_SNAPSHOT_ID`, `RUSTCRAFT_WORLDS` with one world per line and, for local
+/// destinations, `RUSTCRAFT_SNAPSHOT_PATH`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    /// Run in order before the backup. One that fails stops the backup, unless it ignores failures.
+    pub pre_backup: Vec<HookCommand>,
+    /// Run in order after the backup, whether it succeeded or not.
+    pub post_backup: Vec<HookCommand>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookCommand {
+    /// Run by `sh -c` on Linux and `cmd /C` on Windows.
+    pub command: String,
+    /// Defaults to 300. The hook is stopped and counts as failed after that; on Windows,
+    /// programs it started keep running.
+    pub timeout_seconds: Option<u64>,
+    /// Only log a failure as a warning instead of stopping the backup.
+    pub ignore_failure: bool,
+}
+
+/// RCON connection to a running server, used to pause saving during backups.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RconSettings {
+    pub host: String,
+    /// Defaults to 25575.
+    pub port: Option<u16>,
+    /// `rcon.password` from `server.properties`.
+    pub password: String,
+    /// Tell players in chat when a backup starts and ends.
+    pub announce: bool,
+    /// How long to wait for the server to answer, e.g. while it flushes the world. Defaults to 120.
+    pub timeout_seconds: Option<u64>,
 }
@@ -0,0 +1,151 @@
+use std::{collections::BTreeMap, io};
+
+use crate::file_operations::{read_manifest, ManifestEntry};
+use crate::nbt::{self, Tag};
+use crate::region;
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// What changed between two snapshots. Paths are relative to the snapshot root.
+#[derive(Debug, Default)]
+pub struct SnapshotDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+    /// Modified region files and how many of their chunks changed.
+    pub regions: Vec<(String, usize)>,
+    /// Player files under `playerdata/` that were added, removed or modified.
+    pub players: Vec<String>,
+    /// Differences in `level.dat`, one line per field.
+    pub level: Vec<String>,
+}
+
+/// `level.dat` fields worth comparing, as paths below `Data`.
+const LEVEL_FIELDS: [&[&str]; 8] = [
+    &["LevelName"],
+    &["Version", "Name"],
+    &["DataVersion"],
+    &["Time"],
+    &["DayTime"],
+    &["LastPlayed"],
+    &["GameType"],
+    &["Difficulty"],
+];
+
+/// Compare two snapshots file by file using their manifests, then look inside the region
+/// files and `level.dat` files that changed.
+pub fn diff_snapshots(
+    backend: &dyn StorageBackend,
+    old_id: &str,
+    new_id: &str,
+) -> io::Result<SnapshotDiff> {
+    let old: BTreeMap<String, ManifestEntry> = read_manifest(backend, old_id)?
+        .files
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let new: BTreeMap<String, ManifestEntry> = read_manifest(backend, new_id)?
+        .files
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+
+    let mut diff = SnapshotDiff::default();
+    for path in new.keys().filter(|path| !old.contains_key(*path)) {
+        diff.added.push(path.clone());
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        diff.removed.push(path.clone());
+    }
+    for (path, entry) in &new {
+        if old
+            .get(path)
+            .is_some_and(|previous| previous.sha256 != entry.sha256)
+        {
+            diff.modified.push(path.clone());
+        }
+    }
+
+    diff.players = diff
+        .added
+        .iter()
+        .chain(&diff.removed)
+        .chain(&diff.modified)
+        .filter(|path| is_player_file(path))
+        .cloned()
+        .collect();
+    diff.players.sort();
+
+    for path in &diff.modified {
+        if region::is_region_file(path) {
+            let old_data = read_object(backend, old_id, path)?;
+            let new_data = read_object(backend, new_id, path)?;
+            diff.regions
+                .push((path.clone(), region::changed_chunks(&old_data, &new_data)));
+        } else if path == "level.dat" || path.ends_with("/level.dat") {
+            let world = path.strip_suffix("level.dat").unwrap_or_default();
+            let old_level = nbt::parse_gzip(&read_object(backend, old_id, path)?);
+            let new_level = nbt::parse_gzip(&read_object(backend, new_id, path)?);
+            // Bedrock's level.dat is not gzipped; the file list already shows it changed
+            if let (Ok(old_level), Ok(new_level)) = (old_level, new_level) {
+                for line in diff_level(&old_level, &new_level) {
+                    diff.level.push(format!("{}{}", world, line));
+                }
+            }
+        }
+    }
+    Ok(diff)
+}
+
+fn is_player_file(path: &str) -> bool {
+    path.rsplit('/').nth(1) == Some("playerdata") && path.ends_with(".dat")
+}
+
+fn read_object(backend: &dyn StorageBackend, snapshot_id: &str, path: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    backend.get(&join_key(&[snapshot_id, path]), &mut data)?;
+    Ok(data)
+}
+
+/// Lines like `Time: 1200 -> 24000` for every compared field or game rule that differs.
+fn diff_level(old: &Tag, new: &Tag) -> Vec<String> {
+    let old = old.get("Data");
+    let new = new.get("Data");
+    let lookup = |data: Option<&Tag>, path: &[&str]| {
+        path.iter()
+            .try_fold(data?, |tag, key| tag.get(key))
+            .map(|tag| tag.to_string())
+    };
+
+    let mut lines = Vec::new();
+    let mut compare = |name: String, before: Option<String>, after: Option<String>| {
+        if before != after {
+            lines.push(format!(
+                "{}: {} -> {}",
+                name,
+                before.as_deref().unwrap_or("(none)"),
+                after.as_deref().unwrap_or("(none)")
+            ));
+        }
+    };
+
+    for path in LEVEL_FIELDS {
+        compare(path.join("."), lookup(old, path), lookup(new, path));
+    }
+
+    let rules = |data: Option<&Tag>| match data.and_then(|data| data.get("GameRules")) {
+        Some(Tag::Compound(rules)) => rules.clone(),
+        _ => BTreeMap::new(),
+    };
+    let (old_rules, new_rules) = (rules(old), rules(new));
+    let mut names: Vec<&String> = old_rules.keys().chain(new_rules.keys()).collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        compare(
+            format!("GameRules.{}", name),
+            old_rules.get(name).map(Tag::to_string),
+            new_rules.get(name).map(Tag::to_string),
+        );
+    }
+    lines
+}
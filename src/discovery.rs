@@ -0,0 +1,247 @@
+use crate::{bedrock, nbt};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A saves folder found on this machine.
+#[derive(Debug, Clone)]
+pub struct DiscoveredSaves {
+    pub launcher: &'static str,
+    /// Friendly instance name, as shown in the launcher.
+    pub name: String,
+    pub path: PathBuf,
+    /// One line per world, with the name shown in game.
+    pub worlds: Vec<String>,
+}
+
+/// Where the official launcher keeps saves on this platform. The first folder that
+/// exists wins, otherwise the usual location is returned.
+pub fn vanilla_saves_path() -> Option<PathBuf> {
+    let candidates = vanilla_saves_candidates();
+    candidates
+        .iter()
+        .find(|dir| dir.is_dir())
+        .cloned()
+        .or_else(|| candidates.into_iter().next())
+}
+
+fn vanilla_saves_candidates() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let minecraft_dirs = if cfg!(target_os = "windows") {
+        vec![home.join("AppData").join("Roaming").join(".minecraft")]
+    } else if cfg!(target_os = "macos") {
+        vec![home
+            .join("Library")
+            .join("Application Support")
+            .join("minecraft")]
+    } else {
+        vec![
+            home.join(".minecraft"),
+            // Flatpak build of the official launcher
+            home.join(".var")
+                .join("app")
+                .join("com.mojang.Minecraft")
+                .join(".minecraft"),
+        ]
+    };
+    minecraft_dirs.iter().map(|dir| dir.join("saves")).collect()
+}
+
+/// Every saves folder of every launcher RustCraft knows about.
+pub fn discover_saves() -> Vec<DiscoveredSaves> {
+    let mut found = Vec::new();
+
+    for path in vanilla_saves_candidates() {
+        if path.is_dir() {
+            found.push(DiscoveredSaves {
+                launcher: "Minecraft Launcher",
+                name: "Default".to_string(),
+                worlds: java_worlds(&path),
+                path,
+            });
+        }
+    }
+
+    for (name, path) in bedrock::worlds_folders() {
+        if path.is_dir() {
+            found.push(DiscoveredSaves {
+                launcher: "Minecraft Bedrock",
+                name,
+                worlds: bedrock::list_worlds(&path)
+                    .iter()
+                    .map(|world| world.summary())
+                    .collect(),
+                path,
+            });
+        }
+    }
+
+    for (launcher, root) in mmc_instance_roots() {
+        found.extend(scan_instances(launcher, &root, mmc_instance));
+    }
+    if let Some(home) = dirs::home_dir() {
+        found.extend(scan_instances(
+            "CurseForge",
+            &home.join("curseforge").join("minecraft").join("Instances"),
+            curseforge_instance,
+        ));
+    }
+    if let Some(data) = dirs::data_dir() {
+        found.extend(scan_instances(
+            "ATLauncher",
+            &data.join("ATLauncher").join("instances"),
+            atlauncher_instance,
+        ));
+        for app_dir in ["ModrinthApp", "com.modrinth.theseus"] {
+            found.extend(scan_instances(
+                "Modrinth App",
+                &data.join(app_dir).join("profiles"),
+                modrinth_profile,
+            ));
+        }
+    }
+
+    found.sort_by(|a, b| (a.launcher, &a.name).cmp(&(b.launcher, &b.name)));
+    found
+}
+
+/// Instance folders of MultiMC and its forks, which all share the same layout.
+fn mmc_instance_roots() -> Vec<(&'static str, PathBuf)> {
+    let mut roots = Vec::new();
+    if let Some(data) = dirs::data_dir() {
+        roots.push((
+            "Prism Launcher",
+            data.join("PrismLauncher").join("instances"),
+        ));
+        roots.push(("PolyMC", data.join("PolyMC").join("instances")));
+        roots.push(("MultiMC", data.join("multimc").join("instances")));
+    }
+    if let Some(home) = dirs::home_dir() {
+        roots.push((
+            "Prism Launcher",
+            home.join(".var")
+                .join("app")
+                .join("org.prismlauncher.PrismLauncher")
+                .join("data")
+                .join("PrismLauncher")
+                .join("instances"),
+        ));
+    }
+    roots
+}
+
+/// Run `read_instance` on every folder in `root`, keeping the ones with a saves folder.
+fn scan_instances(
+    launcher: &'static str,
+    root: &Path,
+    read_instance: fn(&Path) -> Option<(String, PathBuf)>,
+) -> Vec<DiscoveredSaves> {
+    let Ok(entries) = fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let (name, path) = read_instance(&entry.path())?;
+            path.is_dir().then_some(DiscoveredSaves {
+                launcher,
+                name,
+                worlds: java_worlds(&path),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Summaries of the Java worlds in a saves folder, read from each `level.dat`.
+fn java_worlds(saves: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(saves) else {
+        return Vec::new();
+    };
+    let mut worlds: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|world| world.join("level.dat").is_file())
+        .map(|world| {
+            let level = nbt::read_gzip_file(&world.join("level.dat")).ok();
+            let data = level.as_ref().and_then(|level| level.get("Data"));
+            let name = data
+                .and_then(|data| data.get("LevelName"))
+                .and_then(|name| name.as_str())
+                .map(String::from)
+                .unwrap_or_else(|| folder_name(&world));
+            match data
+                .and_then(|data| data.get("Version"))
+                .and_then(|version| version.get("Name"))
+                .and_then(|version| version.as_str())
+            {
+                Some(version) => format!("{} ({})", name, version),
+                None => name,
+            }
+        })
+        .collect();
+    worlds.sort();
+    worlds
+}
+
+fn folder_name(instance: &Path) -> String {
+    instance
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// `instance.cfg` is an INI file with the display name under `name=`.
+fn mmc_instance(instance: &Path) -> Option<(String, PathBuf)> {
+    let name = fs::read_to_string(instance.join("instance.cfg"))
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("name=")
+                .map(|name| name.trim().to_string())
+        })
+        .unwrap_or_else(|| folder_name(instance));
+    // Older MultiMC versions used `minecraft` instead of `.minecraft`
+    let saves = [".minecraft", "minecraft"]
+        .iter()
+        .map(|dir| instance.join(dir).join("saves"))
+        .find(|saves| saves.is_dir())?;
+    Some((name, saves))
+}
+
+fn curseforge_instance(instance: &Path) -> Option<(String, PathBuf)> {
+    let name = read_json(&instance.join("minecraftinstance.json"))
+        .and_then(|json| json["name"].as_str().map(String::from))
+        .unwrap_or_else(|| folder_name(instance));
+    Some((name, instance.join("saves")))
+}
+
+fn atlauncher_instance(instance: &Path) -> Option<(String, PathBuf)> {
+    let name = read_json(&instance.join("instance.json"))
+        .and_then(|json| {
+            json["launcher"]["name"]
+                .as_str()
+                .or_else(|| json["name"].as_str())
+                .map(String::from)
+        })
+        .unwrap_or_else(|| folder_name(instance));
+    Some((name, instance.join("saves")))
+}
+
+/// Older Modrinth App versions keep a `profile.json` per profile; newer ones store
+/// profiles in a database, where the folder name is the best name available.
+fn modrinth_profile(profile: &Path) -> Option<(String, PathBuf)> {
+    let name = read_json(&profile.join("profile.json"))
+        .and_then(|json| json["metadata"]["name"].as_str().map(String::from))
+        .unwrap_or_else(|| folder_name(profile));
+    Some((name, profile.join("saves")))
+}
@@ -0,0 +1,203 @@
+use std::{error::Error, io};
+
+/// A failed operation, as shown in the error banner until it is dismissed.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// What was being done, like `Backup failed`.
+    pub title: String,
+    pub message: String,
+    /// The error followed by each of its causes.
+    pub chain: Vec<String>,
+    pub fix: Option<&'static str>,
+    /// How many times in a row this same failure happened.
+    pub repeats: usize,
+}
+
+impl Failure {
+    pub fn new(title: &str, error: &io::Error) -> Self {
+        Self {
+            title: title.to_string(),
+            message: error.to_string(),
+            chain: error_chain(error),
+            fix: suggested_fix(error),
+            repeats: 1,
+        }
+    }
+
+    /// Whether `other` is the same failure again, so the two are shown as one.
+    pub fn same_as(&self, other: &Failure) -> bool {
+        self.title == other.title && self.message == other.message
+    }
+}
+
+/// An error's message followed by the messages of its sources, outermost first.
+pub fn error_chain(error: &(dyn Error + 'static)) -> Vec<String> {
+    let mut chain = vec![error.to_string()];
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        // Errors wrapped with `io::Error::other` often repeat their source's message
+        if chain.last() != Some(&message) {
+            chain.push(message);
+        }
+        source = cause.source();
+    }
+    chain
+}
+
+/// Causes of errors the user can do something about, each with the fix to suggest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    NoDestination,
+    NoMinecraftDirectory,
+    WorldOpen,
+    Rcon,
+    NotEnoughSpace,
+    DestinationInsideSource,
+}
+
+impl Cause {
+    fn fix(self) -> &'static str {
+        match self {
+            Cause::NoDestination => "Pick a backup folder first.",
+            Cause::NoMinecraftDirectory => "Pick your Minecraft saves folder first.",
+            Cause::WorldOpen => "Close the world in Minecraft and try again.",
+            Cause::Rcon => {
+                "Check enable-rcon, rcon.port and rcon.password in the server's server.properties."
+            }
+            Cause::NotEnoughSpace => "Free up space in the destination, or keep fewer snapshots.",
+            Cause::DestinationInsideSource => "Pick a backup folder outside the Minecraft folder.",
+        }
+    }
+}
+
+/// What the user can most likely do about an error, when there is a common cause.
+/// Known causes are looked up by type, so rewording a message never changes the advice.
+fn suggested_fix(error: &io::Error) -> Option<&'static str> {
+    if let Some(cause) = cause_of(error) {
+        return Some(cause.fix());
+    }
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => {
+            Some("Check that RustCraft may read the worlds and write to the backup folder.")
+        }
+        io::ErrorKind::NotFound => {
+            Some("Check that the folder still exists. External drives may be unplugged.")
+        }
+        io::ErrorKind::StorageFull => Some(Cause::NotEnoughSpace.fix()),
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::TimedOut => {
+            Some("Check that the server is running and reachable from this computer.")
+        }
+        _ => None,
+    }
+}
+
+// The known cause of an error, looking through every error it wraps
+fn cause_of(error: &io::Error) -> Option<Cause> {
+    let mut current: Option<&(dyn Error + 'static)> = Some(error);
+    while let Some(error) = current {
+        if let Some(known) = error.downcast_ref::<Known>() {
+            return Some(known.cause);
+        }
+        if let Some(cause) = error
+            .downcast_ref::<Skipped>()
+            .and_then(|skipped| skipped.cause)
+        {
+            return Some(cause);
+        }
+        // `io::Error` reports the source of the error it holds, not that error itself
+        current = match error.downcast_ref::<io::Error>() {
+            Some(error) => error.get_ref().map(|inner| inner as &(dyn Error + 'static)),
+            None => error.source(),
+        };
+    }
+    None
+}
+
+/// An error whose cause is known.
+#[derive(Debug)]
+struct Known {
+    cause: Cause,
+    message: String,
+}
+
+impl std::fmt::Display for Known {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Known {}
+
+/// An error with a known cause, so the banner can suggest the fix for it.
+pub fn known(cause: Cause, message: impl Into<String>) -> io::Error {
+    io::Error::other(Known {
+        cause,
+        message: message.into(),
+    })
+}
+
+/// An error saying what was being done when `source` happened. It keeps the kind and
+/// cause of `source`, and shows its message after the context.
+#[derive(Debug)]
+struct Context {
+    message: String,
+    source: io::Error,
+}
+
+impl std::fmt::Display for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.message, self.source)
+    }
+}
+
+impl Error for Context {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+pub fn context(source: io::Error, message: impl Into<String>) -> io::Error {
+    io::Error::new(
+        source.kind(),
+        Context {
+            message: message.into(),
+            source,
+        },
+    )
+}
+
+/// Why a backup was deliberately not taken, as opposed to failing halfway.
+#[derive(Debug)]
+struct Skipped {
+    reason: String,
+    cause: Option<Cause>,
+}
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl Error for Skipped {}
+
+/// An error for a backup that was refused before anything was written.
+pub fn skipped(reason: String) -> io::Error {
+    io::Error::other(Skipped {
+        reason,
+        cause: None,
+    })
+}
+
+/// Like `skipped`, for a refusal with a known cause.
+pub fn skipped_because(cause: Cause, reason: String) -> io::Error {
+    io::Error::other(Skipped {
+        reason,
+        cause: Some(cause),
+    })
+}
+
+pub fn is_skipped(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|inner| inner.is::<Skipped>())
+}
@@ -1,50 +1,553 @@
-use chrono::{DateTime, Local};
-use std::{fs, io, path::Path};
+use chrono::{DateTime, Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
 
-pub fn copy_directory(src: &Path, dst: &Path) -> io::Result<()> {
-    println!("Attempting to copy from {:?} to {:?}", src, dst);
+use tracing::{error, info, warn};
+
+use crate::bedrock;
+use crate::errors::{self, Cause};
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// Name of the manifest stored at the root of every snapshot.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Folder at the root of every snapshot for files RustCraft keeps next to it, like the map
+/// preview. A folder of that name at the root of a source is not backed up, so the two
+/// never mix.
+pub const RESERVED_FOLDER: &str = ".rustcraft";
+
+/// Format used for snapshot folder names. Ensures no illegal characters for file paths.
+/// A second snapshot taken in the same second gets `-2` appended, and so on.
+const SNAPSHOT_ID_FORMAT: &str = "%d.%m.%Y %H.%M.%S";
+
+/// Marker in the reserved folder of a safety snapshot, taken before a restore so it can be
+/// undone. Retention never removes those.
+const SAFETY_MARKER: &str = "safety";
+
+/// Describes the contents of a snapshot, so it can be verified and restored
+/// without trusting whatever happens to be in the destination.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub id: String,
+    pub created: DateTime<Local>,
+    pub source: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the source directory, using `/` as the separator.
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Result of checking a snapshot against its manifest.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub problems: Vec<String>,
+}
+
+/// Check a snapshot `copy_directory` just took was written correctly and apply the
+/// retention policy. Both one-time and scheduled backups run this after the copy; it
+/// only reads the destination, so a server can already be saving again.
+pub fn finish_backup(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    keep_snapshots: usize,
+) -> io::Result<()> {
+    let report = verify_snapshot(backend, snapshot_id)?;
+    if !report.problems.is_empty() {
+        return Err(io::Error::other(format!(
+            "Snapshot {} failed verification: {}",
+            snapshot_id,
+            report.problems.join(", ")
+        )));
+    }
+
+    for expired in prune_snapshots(backend, keep_snapshots)? {
+        info!("Removed expired snapshot: {}", expired);
+    }
+    Ok(())
+}
+
+/// Snapshot `src` into `backend`, returning the new snapshot's id.
+pub fn copy_directory(src: &Path, backend: &dyn StorageBackend) -> io::Result<String> {
+    take_snapshot(src, None, false, backend)
+}
+
+/// Snapshot what a restore into `src` is about to replace, so it can be undone. `paths` are
+/// relative to `src` with folders ending in `/`, or `None` for all of it. The snapshot is
+/// verified before this returns, and retention never removes it.
+pub fn take_safety_snapshot(
+    src: &Path,
+    paths: Option<&[String]>,
+    backend: &dyn StorageBackend,
+) -> io::Result<String> {
+    let snapshot_id = take_snapshot(src, paths, true, backend)?;
+    let report = verify_snapshot(backend, &snapshot_id)?;
+    if !report.problems.is_empty() {
+        return Err(io::Error::other(format!(
+            "Safety snapshot {} failed verification, so nothing was restored: {}",
+            snapshot_id,
+            report.problems.join(", ")
+        )));
+    }
+    Ok(snapshot_id)
+}
+
+fn take_snapshot(
+    src: &Path,
+    paths: Option<&[String]>,
+    safety: bool,
+    backend: &dyn StorageBackend,
+) -> io::Result<String> {
+    info!(
+        "Attempting to copy from {:?} to {}",
+        src,
+        backend.describe()
+    );
     let local: DateTime<Local> = Local::now();
-    let timestamp = local.format("%d.%m.%Y %H.%M.%S").to_string(); // Ensure no illegal characters for file paths
-    let dst_with_timestamp = dst.join(timestamp);
-    println!("Creating directory: {:?}", dst_with_timestamp);
+    let snapshot_id = new_snapshot_id(backend, &local)?;
+    info!("Creating snapshot: {}", snapshot_id);
 
-    fs::create_dir_all(&dst_with_timestamp)?;
+    let mut manifest = Manifest {
+        id: snapshot_id.clone(),
+        created: local,
+        source: src.to_string_lossy().into_owned(),
+        files: Vec::new(),
+    };
 
-    // Recursively copy all contents from src to the new destination directory
-    match copy_contents_recursively(src, src, &dst_with_timestamp) {
+    // Recursively copy all contents from src into the snapshot. The safety marker goes in
+    // before the manifest, so a listed safety snapshot always has it.
+    let result = copy_contents_recursively(src, src, paths, &snapshot_id, backend, &mut manifest)
+        .and_then(|()| {
+            if safety {
+                backend.put(&safety_marker_key(&snapshot_id), &mut io::empty())?;
+            }
+            write_manifest(backend, &manifest)
+        });
+    match result {
         Ok(()) => {
-            eprintln!("Backup completed successfully");
-            Ok(())
+            info!("Backup completed successfully");
+            Ok(snapshot_id)
         }
         Err(e) => {
-            let err_msg = format!("Failed to copy directory: {:?}", e);
-            eprintln!("Backup Error: {}", err_msg);
-            // Return the error with details
-            Err(io::Error::other(err_msg))
+            error!("Backup Error: Failed to copy directory: {}", e);
+            // Without a manifest the partial snapshot is never listed, so nothing else would
+            // ever remove it
+            if let Err(cleanup) = delete_objects(backend, &snapshot_id) {
+                warn!(
+                    "Could not remove the partial snapshot {}: {}",
+                    snapshot_id, cleanup
+                );
+            }
+            // Keep the error as the source, so its kind and cause still pick the suggested fix
+            Err(errors::context(e, "Failed to copy directory"))
         }
     }
 }
 
-/// Recursively copies contents from the source directory to the destination directory, maintaining the structure.
-fn copy_contents_recursively(base: &Path, src: &Path, dst: &Path) -> io::Result<()> {
+// Id for a snapshot taken at `time`, numbered when the destination already holds one
+// from the same second
+fn new_snapshot_id(backend: &dyn StorageBackend, time: &DateTime<Local>) -> io::Result<String> {
+    let base = time.format(SNAPSHOT_ID_FORMAT).to_string();
+    let mut snapshot_id = base.clone();
+    let mut number = 1;
+    while !backend.list(&snapshot_id)?.is_empty() {
+        number += 1;
+        snapshot_id = format!("{}-{}", base, number);
+    }
+    Ok(snapshot_id)
+}
+
+fn delete_objects(backend: &dyn StorageBackend, prefix: &str) -> io::Result<()> {
+    for object in backend.list(prefix)? {
+        backend.delete(&object.key)?;
+    }
+    Ok(())
+}
+
+fn safety_marker_key(snapshot_id: &str) -> String {
+    join_key(&[snapshot_id, RESERVED_FOLDER, SAFETY_MARKER])
+}
+
+/// Recursively copies contents from the source directory into the snapshot, maintaining the structure.
+/// With `paths`, only those files and folders below `base` are copied.
+fn copy_contents_recursively(
+    base: &Path,
+    src: &Path,
+    paths: Option<&[String]>,
+    snapshot_id: &str,
+    backend: &dyn StorageBackend,
+    manifest: &mut Manifest,
+) -> io::Result<()> {
     for entry in fs::read_dir(src)? {
         let entry = entry?;
         let path = entry.path();
-        // Get the relative path with respect to the base
-        let relative_path = path.strip_prefix(base).unwrap();
-        let destination_path = dst.join(relative_path);
 
-        if entry.file_type()?.is_dir() {
-            fs::create_dir_all(&destination_path)?;
+        if src == base && entry.file_name() == RESERVED_FOLDER {
+            continue;
+        }
+        let is_dir = entry.file_type()?.is_dir();
+        if let Some(paths) = paths {
+            let relative = relative_key(base, &path);
+            let folder = format!("{}/", relative);
+            let wanted = paths.iter().any(|wanted| {
+                if is_dir {
+                    // Inside a wanted folder, or on the way to something wanted
+                    folder.starts_with(wanted.as_str()) || wanted.starts_with(&folder)
+                } else {
+                    *wanted == relative
+                        || (wanted.ends_with('/') && relative.starts_with(wanted.as_str()))
+                }
+            });
+            if !wanted {
+                continue;
+            }
+        }
+        if is_dir {
+            // Bedrock's LevelDB only makes sense as a whole, so keep the game out while copying it
+            let _lock = if bedrock::is_leveldb(&path) {
+                Some(bedrock::lock_leveldb(&path)?)
+            } else {
+                None
+            };
             // Recursive call to handle subdirectories
-            copy_contents_recursively(base, &path, &destination_path)?;
-        } else {
-            if let Some(parent) = destination_path.parent() {
-                fs::create_dir_all(parent)?; // Ensure the directory exists
+            copy_contents_recursively(base, &path, paths, snapshot_id, backend, manifest)?;
+        } else if !bedrock::is_leveldb_lock(&path) {
+            // Get the relative path with respect to the base
+            let relative_path = relative_key(base, &path);
+            let mut reader = HashingReader::new(File::open(&path)?);
+            let size = backend.put(&join_key(&[snapshot_id, &relative_path]), &mut reader)?;
+            manifest.files.push(ManifestEntry {
+                path: relative_path,
+                size,
+                sha256: reader.finish(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn write_manifest(backend: &dyn StorageBackend, manifest: &Manifest) -> io::Result<()> {
+    let data = serde_json::to_vec_pretty(manifest)?;
+    backend.put(
+        &join_key(&[&manifest.id, MANIFEST_FILE]),
+        &mut data.as_slice(),
+    )?;
+    Ok(())
+}
+
+pub fn read_manifest(backend: &dyn StorageBackend, snapshot_id: &str) -> io::Result<Manifest> {
+    let mut data = Vec::new();
+    backend.get(&join_key(&[snapshot_id, MANIFEST_FILE]), &mut data)?;
+    serde_json::from_slice(&data).map_err(io::Error::other)
+}
+
+/// Ids of every snapshot in `backend`, oldest first, safety snapshots included.
+///
+/// Only folders with a manifest count as snapshots, so retention never touches
+/// anything RustCraft did not write itself.
+pub fn list_snapshots(backend: &dyn StorageBackend) -> io::Result<Vec<String>> {
+    Ok(list_marked_snapshots(backend)?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect())
+}
+
+/// Ids of the safety snapshots in `backend`, oldest first.
+pub fn list_safety_snapshots(backend: &dyn StorageBackend) -> io::Result<Vec<String>> {
+    Ok(list_marked_snapshots(backend)?
+        .into_iter()
+        .filter(|(_, safety)| *safety)
+        .map(|(id, _)| id)
+        .collect())
+}
+
+// Every snapshot with whether it is a safety snapshot, from a single listing
+fn list_marked_snapshots(backend: &dyn StorageBackend) -> io::Result<Vec<(String, bool)>> {
+    let objects = backend.list("")?;
+    let mut snapshots: Vec<(NaiveDateTime, u32, String)> = objects
+        .iter()
+        .filter_map(|object| {
+            let (id, file) = object.key.split_once('/')?;
+            if file != MANIFEST_FILE {
+                return None;
             }
-            // println!("Copying file {:?} to {:?}", path, destination_path);
-            fs::copy(&path, &destination_path)?;
+            let (time, rest) = NaiveDateTime::parse_and_remainder(id, SNAPSHOT_ID_FORMAT).ok()?;
+            let number = match rest {
+                "" => 1,
+                rest => rest.strip_prefix('-')?.parse().ok()?,
+            };
+            Some((time, number, id.to_string()))
+        })
+        .collect();
+    snapshots.sort();
+    Ok(snapshots
+        .into_iter()
+        .map(|(_, _, id)| {
+            let marker = safety_marker_key(&id);
+            let safety = objects.iter().any(|object| object.key == marker);
+            (id, safety)
+        })
+        .collect())
+}
+
+/// Fail if Minecraft has `world` open: Java Edition locks `session.lock` while a world is
+/// loaded, and anything written into the world then would be overwritten by the game.
+pub fn ensure_world_closed(world: &Path) -> io::Result<()> {
+    let session_lock = world.join("session.lock");
+    if !session_lock.exists() {
+        return Ok(());
+    }
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&session_lock)?;
+    if try_lock_exclusive(&file)? {
+        Ok(())
+    } else {
+        Err(errors::known(
+            Cause::WorldOpen,
+            format!(
+                "{} is open in Minecraft. Close the world and try again",
+                world.display()
+            ),
+        ))
+    }
+}
+
+/// Lock `file` without waiting, returning `false` when another process holds a lock on it.
+/// The lock lasts until the file is closed.
+///
+/// On Unix this is an `fcntl` lock, the kind Java's `FileChannel.tryLock` and LevelDB take;
+/// `flock` locks, which `File::try_lock` uses there, never see theirs.
+#[cfg(unix)]
+pub fn try_lock_exclusive(file: &File) -> io::Result<bool> {
+    use rustix::{
+        fs::{fcntl_lock, FlockOperation},
+        io::Errno,
+    };
+    match fcntl_lock(file, FlockOperation::NonBlockingLockExclusive) {
+        Ok(()) => Ok(true),
+        // POSIX allows either for a lock held elsewhere
+        Err(Errno::AGAIN | Errno::ACCESS) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Lock `file` without waiting, returning `false` when another process holds a lock on it.
+/// The lock lasts until the file is closed.
+#[cfg(not(unix))]
+pub fn try_lock_exclusive(file: &File) -> io::Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(fs::TryLockError::WouldBlock) => Ok(false),
+        Err(fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Copy every file of a snapshot into `dst`, overwriting what is already there.
+///
+/// Bedrock databases are replaced as a whole: LevelDB files left over from the live
+/// world would not match the restored ones and Minecraft would refuse to open it.
+pub fn restore_snapshot(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    dst: &Path,
+) -> io::Result<()> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    // Every path is checked before anything in `dst` is touched
+    let destinations = manifest
+        .files
+        .iter()
+        .map(|entry| restore_path(dst, &entry.path))
+        .collect::<io::Result<Vec<_>>>()?;
+    for entry in &manifest.files {
+        let Some(world) = entry
+            .path
+            .strip_suffix("db/CURRENT")
+            .filter(|world| world.is_empty() || world.ends_with('/'))
+        else {
+            continue;
+        };
+        let db = restore_path(dst, world)?.join(bedrock::LEVELDB_FOLDER);
+        if db.is_dir() {
+            // Only checks the world is not open; the lock goes away with the folder
+            drop(bedrock::lock_leveldb(&db)?);
+            fs::remove_dir_all(&db)?;
+        }
+    }
+
+    for (entry, destination_path) in manifest.files.iter().zip(destinations) {
+        if let Some(parent) = destination_path.parent() {
+            fs::create_dir_all(parent)?; // Ensure the directory exists
         }
+        let mut file = File::create(&destination_path)?;
+        backend.get(&join_key(&[snapshot_id, &entry.path]), &mut file)?;
     }
     Ok(())
 }
+
+/// Where a snapshot's `relative` path goes when restoring into `dst`. Manifests come from
+/// the destination, so a path that would leave `dst`, through `..` or as an absolute path,
+/// is refused rather than trusted.
+pub fn restore_path(dst: &Path, relative: &str) -> io::Result<PathBuf> {
+    let outside = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Refusing to restore {}: it points outside the folder",
+                relative
+            ),
+        )
+    };
+    if relative.starts_with('/') {
+        return Err(outside());
+    }
+    let mut path = dst.to_path_buf();
+    for segment in relative.split('/').filter(|segment| !segment.is_empty()) {
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => path.push(name),
+            _ => return Err(outside()),
+        }
+    }
+    Ok(path)
+}
+
+/// Delete the oldest snapshots so that at most `keep` remain. A `keep` of 0 keeps everything.
+/// Safety snapshots neither count nor get deleted. Returns the ids of the deleted snapshots.
+pub fn prune_snapshots(backend: &dyn StorageBackend, keep: usize) -> io::Result<Vec<String>> {
+    let snapshots: Vec<String> = list_marked_snapshots(backend)?
+        .into_iter()
+        .filter(|(_, safety)| !safety)
+        .map(|(id, _)| id)
+        .collect();
+    if keep == 0 || snapshots.len() <= keep {
+        return Ok(Vec::new());
+    }
+
+    let expired = snapshots[..snapshots.len() - keep].to_vec();
+    for snapshot_id in &expired {
+        // Remove the manifest last, so a partially deleted snapshot is still listed
+        // and cleaned up by the next run.
+        for object in backend.list(snapshot_id)? {
+            if object.key != join_key(&[snapshot_id, MANIFEST_FILE]) {
+                backend.delete(&object.key)?;
+            }
+        }
+        backend.delete(&join_key(&[snapshot_id, MANIFEST_FILE]))?;
+    }
+    Ok(expired)
+}
+
+/// Read back every file in a snapshot and compare it with the manifest.
+pub fn verify_snapshot(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+) -> io::Result<VerifyReport> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let mut report = VerifyReport::default();
+
+    for entry in &manifest.files {
+        report.checked += 1;
+        let key = join_key(&[snapshot_id, &entry.path]);
+        match backend.stat(&key)? {
+            None => {
+                report.problems.push(format!("{}: missing", entry.path));
+                continue;
+            }
+            Some(info) if info.size != entry.size => {
+                report.problems.push(format!(
+                    "{}: expected {} bytes, found {}",
+                    entry.path, entry.size, info.size
+                ));
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let mut sink = HashingWriter::default();
+        if let Err(e) = backend.get(&key, &mut sink) {
+            report.problems.push(format!("{}: {}", entry.path, e));
+            continue;
+        }
+        if sink.finish() != entry.sha256 {
+            report
+                .problems
+                .push(format!("{}: checksum mismatch", entry.path));
+        }
+    }
+    Ok(report)
+}
+
+fn relative_key(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap()
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Lowercase hex digits of `bytes`, the way checksums are written in manifests.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hashes everything read through it, so files are only read once during a backup.
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.hasher.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+#[derive(Default)]
+struct HashingWriter {
+    hasher: Sha256,
+}
+
+impl HashingWriter {
+    fn finish(self) -> String {
+        to_hex(&self.hasher.finalize())
+    }
+}
+
+impl Write for HashingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
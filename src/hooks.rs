@@ -0,0 +1,147 @@
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Child, Command, Stdio},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+use crate::config::HookCommand;
+
+/// How long a hook may run when it does not set its own timeout.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Run hooks one after the other, with `env` added to their environment. Stops at the
+/// first failing hook that is not set to `ignore_failure`; the ones that are only end up
+/// in `warnings`.
+pub fn run_all(
+    hooks: &[HookCommand],
+    env: &[(&str, String)],
+    warnings: &mut Vec<String>,
+) -> io::Result<()> {
+    for hook in hooks.iter().filter(|hook| !hook.command.trim().is_empty()) {
+        match run(hook, env) {
+            Ok(()) => {}
+            Err(e) if hook.ignore_failure => warnings.push(e.to_string()),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Run one hook through the system shell, logging its output line by line, and fail
+/// if it exits with an error or runs past its timeout.
+pub fn run(hook: &HookCommand, env: &[(&str, String)]) -> io::Result<()> {
+    let timeout = hook
+        .timeout_seconds
+        .map_or(DEFAULT_TIMEOUT, Duration::from_secs);
+    info!("Running hook `{}`", hook.command);
+
+    let mut child = shell(&hook.command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Hook `{}`: {}", hook.command, e)))?;
+
+    // Lines come through a channel so they are logged here, inside the backup's span
+    let (tx, rx) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout, false, tx.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward_lines(stderr, true, tx);
+    }
+    let log_line = |(is_stderr, line): (bool, String)| {
+        if is_stderr {
+            warn!("[{}] {}", hook.command, line);
+        } else {
+            info!("[{}] {}", hook.command, line);
+        }
+    };
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            stop(&mut child);
+            rx.try_iter().for_each(log_line);
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "Hook `{}` did not finish within {}s",
+                    hook.command,
+                    timeout.as_secs()
+                ),
+            ));
+        }
+        match rx.recv_timeout(Duration::from_millis(100)) {
+            Ok(line) => log_line(line),
+            Err(RecvTimeoutError::Timeout) => {}
+            // Both streams closed; wait for the exit without spinning
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(Duration::from_millis(100)),
+        }
+    };
+    // Whatever is still on its way; a background process the hook started may keep
+    // the streams open, so do not wait for them to close
+    while let Ok(line) = rx.recv_timeout(Duration::from_millis(200)) {
+        log_line(line);
+    }
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "Hook `{}` failed with {}",
+            hook.command, status
+        )))
+    }
+}
+
+// The command goes to cmd as typed: quoting it like a program argument would turn its
+// quotes into `\"`, which cmd does not understand
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").raw_arg(command);
+    shell
+}
+
+// In a process group of its own, so a timeout stops everything the command started
+#[cfg(not(windows))]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut shell = Command::new("sh");
+    shell.args(["-c", command]).process_group(0);
+    shell
+}
+
+// Windows has no process groups to stop; programs the command started keep running
+#[cfg(windows)]
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(not(windows))]
+fn stop(child: &mut Child) {
+    use rustix::process::{kill_process_group, Pid, Signal};
+    let _ = kill_process_group(Pid::from_child(child), Signal::KILL);
+    let _ = child.wait();
+}
+
+fn forward_lines(stream: impl Read + Send + 'static, is_stderr: bool, tx: Sender<(bool, String)>) {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if tx.send((is_stderr, line)).is_err() {
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,74 @@
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+};
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{Builder, Rotation},
+};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    fmt,
+    prelude::*,
+};
+
+use crate::config;
+
+const LOG_PREFIX: &str = "rustcraft";
+const LOG_SUFFIX: &str = "log";
+/// Log files are rotated daily, so this keeps a week of them.
+const KEPT_LOG_FILES: usize = 7;
+
+/// Folder the log files are written to.
+pub fn log_directory() -> PathBuf {
+    config::data_directory().join("logs")
+}
+
+/// Write log events to a new file in the data folder every day, and to stderr for `cargo run`.
+/// `level` is one of `error`, `warn`, `info`, `debug` or `trace` and only applies to RustCraft
+/// itself; libraries only log warnings. Logging stops when the returned guard is dropped.
+pub fn init(level: &str) -> io::Result<WorkerGuard> {
+    let level = level.parse().unwrap_or(LevelFilter::INFO);
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_PREFIX)
+        .filename_suffix(LOG_SUFFIX)
+        .max_log_files(KEPT_LOG_FILES)
+        .build(log_directory())
+        .map_err(io::Error::other)?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::registry()
+        .with(
+            Targets::new()
+                .with_target(env!("CARGO_PKG_NAME"), level)
+                .with_default(LevelFilter::WARN),
+        )
+        .with(fmt::layer().with_writer(writer).with_ansi(false))
+        .with(fmt::layer().with_writer(io::stderr))
+        .try_init()
+        .map_err(io::Error::other)?;
+    Ok(guard)
+}
+
+/// The last `count` lines of the newest log file.
+pub fn recent_lines(count: usize) -> io::Result<Vec<String>> {
+    // Dates in the file names sort the same way as the files' ages
+    let newest = fs::read_dir(log_directory())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(LOG_PREFIX))
+        })
+        .max();
+    let Some(newest) = newest else {
+        return Ok(Vec::new());
+    };
+
+    let mut lines: Vec<String> = BufReader::new(fs::File::open(newest)?)
+        .lines()
+        .collect::<io::Result<_>>()?;
+    let start = lines.len().saturating_sub(count);
+    Ok(lines.split_off(start))
+}
@@ -2,7 +2,10 @@
 
 use iced::font::{self, Font};
 use iced::widget::tooltip::{Position as TooltipPosition, Tooltip};
-use iced::widget::{image::Handle as ImageHandle, Button, Column, Container, Row, Slider, Text};
+use iced::widget::{
+    image::Handle as ImageHandle, scrollable, Button, Checkbox, Column, Container, PickList, Row,
+    Scrollable, Slider, Text, TextInput,
+};
 use iced::Color;
 use iced::{
     alignment::{Horizontal, Vertical},
@@ -14,18 +17,46 @@ use iced::{
 };
 use rfd::FileDialog; // FileDialog for folder selection (cross-platform)
 
+use chrono::Local;
+use tracing::{error, info, info_span, warn};
+
 use std::{
-    path::{Path, PathBuf},
-    sync::mpsc::{self, Receiver, Sender},
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf, MAIN_SEPARATOR},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Mutex, PoisonError,
+    },
     thread,
     time::{Duration, Instant},
 };
 
+mod alerts;
 mod assets;
+mod bedrock;
 mod config;
+mod diff;
+mod discovery;
+mod errors;
 mod file_operations;
+mod hooks;
+mod logging;
+mod map;
+mod nbt;
 mod notification;
+mod packages;
+mod players;
+mod preflight;
+mod rcon;
+mod region;
+mod runs;
+mod selective;
+mod sftp;
+mod smtp;
+mod webhooks;
 extern crate dirs;
+#[cfg(target_os = "windows")]
 extern crate winapi;
 
 mod styling {
@@ -33,6 +64,17 @@ mod styling {
     pub mod button_styles;
     pub mod slider_styles;
 }
+mod storage {
+    pub mod backend;
+    pub mod encrypted;
+    pub mod local;
+    pub mod s3;
+    pub mod sftp;
+    pub mod webdav;
+    mod xml;
+}
+use config::{Config, DestinationKind, SourceKind};
+use storage::backend::StorageBackend;
 use styling::_general_styles::text_sizes;
 use styling::button_styles;
 use styling::slider_styles;
@@ -62,13 +104,29 @@ fn show_system_modal_message(title: &str, message: &str) {
     }
 }
 
-// Log the message to stderr on non-Windows platforms
+// Use the desktop's native dialog (GTK on Linux) elsewhere
 #[cfg(not(target_os = "windows"))]
 fn show_system_modal_message(title: &str, message: &str) {
-    eprintln!("{}: {}", title, message);
+    rfd::MessageDialog::new()
+        .set_title(title)
+        .set_description(message)
+        .set_level(rfd::MessageLevel::Info)
+        .set_buttons(rfd::MessageButtons::Ok)
+        .show();
 }
 
 fn main() {
+    // Under the Windows subsystem there is no console, so the log file is all there is
+    let _log_guard = match logging::init(&config::load_configuration().log_level) {
+        Ok(guard) => Some(guard),
+        Err(e) => {
+            show_system_modal_message("Error", &format!("Could not start logging: {}", e));
+            None
+        }
+    };
+
+    alerts::start_watchdog();
+
     let icon = load_icon().expect("Failed to load icon");
 
     let window_settings = window::Settings {
@@ -98,6 +156,16 @@ fn main() {
     }
 }
 
+// Run blocking work, like a download, on a thread of its own and resolve to its result,
+// so `Command::perform` keeps it off the window and the executor.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    let (tx, rx) = iced::futures::channel::oneshot::channel();
+    thread::spawn(move || {
+        let _ = tx.send(work());
+    });
+    rx.await.expect("background work panicked")
+}
+
 // Load an embedded asset as an iced image, so the app works regardless of
 // the working directory it is launched from.
 fn asset_image(name: &str) -> Image<ImageHandle> {
@@ -107,54 +175,1266 @@ fn asset_image(name: &str) -> Image<ImageHandle> {
 
 // Shorten a filesystem path for display, keeping the last two components.
 fn truncate_path(path: &str) -> String {
-    let components: Vec<&str> = path.split('\\').collect();
+    let components: Vec<Component> = Path::new(path).components().collect();
     if components.len() > 3 {
-        format!("...\\{}", components[components.len() - 2..].join("\\"))
+        let tail: PathBuf = components[components.len() - 2..].iter().collect();
+        format!("...{}{}", MAIN_SEPARATOR, tail.display())
     } else {
         path.to_string()
     }
 }
 
-fn load_icon() -> Result<Icon, image::ImageError> {
-    let icon_data = assets::get_asset("icon.ico").expect("Icon not found in assets");
-    let img = image::load_from_memory(&icon_data)?.to_rgba8();
-    let width = img.width();
-    let height = img.height();
-    let raw_data = img.into_raw();
-    Ok(window::icon::from_rgba(raw_data, width, height).unwrap())
-}
+// Resolve the folder to back up, pulling it from the server first for remote sources.
+fn prepare_source(config: &Config) -> io::Result<PathBuf> {
+    match config.source {
+        SourceKind::Local | SourceKind::Server => config
+            .minecraft_directory
+            .as_ref()
+            .map(PathBuf::from)
+            .ok_or_else(|| {
+                errors::known(
+                    errors::Cause::NoMinecraftDirectory,
+                    "No Minecraft directory selected",
+                )
+            }),
+        SourceKind::Sftp => {
+            let staging = sftp::staging_directory(&config.staging_directory, &config.sftp_source);
+            let report = sftp::pull_directory(&config.sftp_source, &staging)?;
+            info!(
+                "Pulled {} from {}: {} downloaded, {} unchanged, {} removed",
+                config.sftp_source.remote_path,
+                config.sftp_source.host,
+                report.downloaded,
+                report.unchanged,
+                report.removed
+            );
+            Ok(staging)
+        }
+    }
+}
+
+// Where the configured source is, for the run history.
+fn describe_source(config: &Config) -> String {
+    match config.source {
+        SourceKind::Local | SourceKind::Server => {
+            config.minecraft_directory.clone().unwrap_or_default()
+        }
+        SourceKind::Sftp => format!(
+            "sftp://{}{}",
+            config.sftp_source.host, config.sftp_source.remote_path
+        ),
+    }
+}
+
+// Held for the whole of a backup run. Two runs at once would write the same snapshot
+// when they start in the same second, and pause and resume saving over each other.
+static BACKUP_RUNNING: Mutex<()> = Mutex::new(());
+
+// Back up the configured source and append a record of the run to the history, whatever the outcome.
+fn run_backup_recorded(
+    config: &Config,
+    backend: &dyn StorageBackend,
+    trigger: runs::Trigger,
+) -> io::Result<runs::RunRecord> {
+    let _running = BACKUP_RUNNING
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let mut run = runs::RunRecord::new(trigger, backend.describe());
+    run.source = describe_source(config);
+    let span = info_span!("backup", trigger = ?trigger, source = %run.source);
+    let _entered = span.enter();
+    let mut env = vec![
+        ("RUSTCRAFT_SOURCE", run.source.clone()),
+        ("RUSTCRAFT_DESTINATION", run.destination.clone()),
+    ];
+    let result = hooks::run_all(&config.hooks.pre_backup, &env, &mut run.warnings)
+        .map_err(|e| errors::skipped(format!("A pre-backup hook failed. {}", e)))
+        .and_then(|()| back_up_source(config, backend, &mut run.warnings));
+    match &result {
+        Ok(snapshot_id) => {
+            if let Ok(manifest) = file_operations::read_manifest(backend, snapshot_id) {
+                run.files = manifest.files.len();
+                run.bytes = manifest.files.iter().map(|entry| entry.size).sum();
+            }
+            // A snapshot of a single world has it at the root, named after the source folder
+            let source_name = Path::new(&run.source)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            run.worlds = packages::snapshot_worlds(backend, snapshot_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|world| match world.rsplit('/').next() {
+                    Some(name) if !name.is_empty() => name.to_string(),
+                    _ => source_name.clone(),
+                })
+                .collect();
+            run.snapshot_id = Some(snapshot_id.clone());
+        }
+        Err(e) => {
+            error!("Backup failed: {}", e);
+            run.error = Some(e.to_string());
+            run.error_chain = errors::error_chain(e);
+            run.skipped = errors::is_skipped(e);
+        }
+    }
+    if !config.hooks.post_backup.is_empty() {
+        let snapshot_id = run.snapshot_id.clone().unwrap_or_default();
+        let snapshot_path = match (&config.destination, &config.backup_directory) {
+            (DestinationKind::Local, Some(directory)) if !snapshot_id.is_empty() => {
+                Path::new(directory)
+                    .join(&snapshot_id)
+                    .display()
+                    .to_string()
+            }
+            _ => String::new(),
+        };
+        env.extend([
+            (
+                "RUSTCRAFT_RESULT",
+                webhooks::Event::of(&run).name().to_string(),
+            ),
+            ("RUSTCRAFT_ERROR", run.error.clone().unwrap_or_default()),
+            ("RUSTCRAFT_SNAPSHOT_ID", snapshot_id),
+            ("RUSTCRAFT_SNAPSHOT_PATH", snapshot_path),
+            ("RUSTCRAFT_WORLDS", run.worlds.join("\n")),
+        ]);
+        // The backup is done either way, so a failing hook is only worth a warning
+        if let Err(e) = hooks::run_all(&config.hooks.post_backup, &env, &mut run.warnings) {
+            run.warnings.push(e.to_string());
+        }
+    }
+    run.finished = Local::now();
+    for warning in &run.warnings {
+        warn!("{}", warning);
+    }
+    if let Err(e) = runs::append(&run) {
+        warn!("Could not record the backup run: {}", e);
+    }
+    webhooks::send_all(&config.webhooks, &run);
+    alerts::on_run(&config.email, &run);
+    result.map(|_| run)
+}
+
+// Show the outcome of a backup on the desktop, with buttons to act on it where supported.
+fn notify_run(
+    config: &Config,
+    result: &io::Result<runs::RunRecord>,
+    next: Option<chrono::DateTime<Local>>,
+    retry: Sender<()>,
+) {
+    let action_config = config.clone();
+    let on_action = move |action| handle_notification_action(&action_config, action, &retry);
+    match result {
+        Ok(run) => {
+            let mut actions = Vec::new();
+            if config.destination == DestinationKind::Local && config.backup_directory.is_some() {
+                actions.push(notification::Action::OpenBackupFolder);
+            }
+            actions.push(notification::Action::ViewLog);
+            notification::notify_success(&config.notifications, run, next, &actions, on_action);
+        }
+        Err(e) => notification::notify_failure(
+            &config.notifications,
+            &format!("Backup failed: {}", e),
+            &[notification::Action::Retry, notification::Action::ViewLog],
+            on_action,
+        ),
+    }
+}
+
+// Runs on the notification's own thread. A retry is handed to the window, which runs it
+// like any other backup and shows how it went.
+fn handle_notification_action(config: &Config, action: notification::Action, retry: &Sender<()>) {
+    let (folder, result) = match action {
+        notification::Action::OpenBackupFolder => (
+            "backup folder",
+            config
+                .backup_directory
+                .as_ref()
+                .map_or(Ok(()), |directory| {
+                    open_in_file_manager(Path::new(directory))
+                }),
+        ),
+        notification::Action::ViewLog => (
+            "log folder",
+            open_in_file_manager(&logging::log_directory()),
+        ),
+        notification::Action::Retry => {
+            let _ = retry.send(());
+            return;
+        }
+    };
+    if let Err(e) = result {
+        error!("Could not open the {}: {}", folder, e);
+    }
+}
+
+// Back up the configured source into `backend`. Server worlds are copied with saving paused.
+fn back_up_source(
+    config: &Config,
+    backend: &dyn StorageBackend,
+    warnings: &mut Vec<String>,
+) -> io::Result<String> {
+    let src_dir = prepare_source(config)?;
+    preflight::check(config, &src_dir, backend)?;
+
+    // Only this part runs with a server's saving paused. The region check stays in it,
+    // as region files the server is still writing could look damaged.
+    let mut copy = || {
+        if config.check_regions {
+            // A damaged world is still backed up: the rest of it, and every other world
+            // in the folder, is worth keeping
+            let report = region::check_directory(&src_dir)?;
+            if !report.problems.is_empty() {
+                warnings.push(format!("The world is damaged. {}", report.summary()));
+            }
+        }
+        file_operations::copy_directory(&src_dir, backend)
+    };
+    let snapshot_id = match config.source {
+        SourceKind::Server => rcon::with_saving_paused(&config.rcon, copy),
+        SourceKind::Local | SourceKind::Sftp => copy(),
+    }?;
+
+    file_operations::finish_backup(backend, &snapshot_id, config.keep_snapshots)?;
+    if config.render_previews {
+        // A missing preview is rendered later from the snapshot, so this never fails the backup.
+        // A server is saving again by now, so its preview comes from the snapshot instead.
+        let rendered = match config.source {
+            SourceKind::Server => map::load_or_render(backend, &snapshot_id).map(|_| ()),
+            SourceKind::Local | SourceKind::Sftp => {
+                file_operations::read_manifest(backend, &snapshot_id).and_then(|manifest| {
+                    let png = map::render(&manifest, |path| fs::read(src_dir.join(path)))?;
+                    if let Some(png) = png {
+                        backend.put(&map::preview_key(&snapshot_id), &mut png.as_slice())?;
+                    }
+                    Ok(())
+                })
+            }
+        };
+        if let Err(e) = rendered {
+            warnings.push(format!("Could not render a map preview: {}", e));
+        }
+    }
+    Ok(snapshot_id)
+}
+
+// Ask before restoring parts into a folder other than the one the snapshot was taken from,
+// as its other files would then not belong with the restored ones
+fn confirm_restore_target(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    target: &Path,
+) -> io::Result<bool> {
+    let Some(mismatch) = selective::target_mismatch(backend, snapshot_id, target)? else {
+        return Ok(true);
+    };
+    warn!("{}", mismatch);
+    let answer = rfd::MessageDialog::new()
+        .set_title("Restore into a different folder?")
+        .set_description(format!("{} Restore into it anyway?", mismatch))
+        .set_level(rfd::MessageLevel::Warning)
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show();
+    Ok(answer == rfd::MessageDialogResult::Yes)
+}
+
+// Shown wherever encrypted backups are made or restored: without the passphrase
+// there is no way to get the worlds back.
+fn encryption_warning<'a>() -> Element<'a, Message> {
+    Tooltip::new(
+        Text::new("Encrypted - don't lose your passphrase!")
+            .font(MONOCRAFT)
+            .size(text_sizes::SECONDARY)
+            .style(theme::Text::Color(Color::from_rgb8(0xd9, 0x7a, 0x1e))),
+        Text::new(
+            "Backups are encrypted with your passphrase. If it is lost, \
+             nobody can restore them, not even RustCraft.",
+        )
+        .font(MONOCRAFT)
+        .size(text_sizes::SECONDARY),
+        TooltipPosition::Bottom,
+    )
+    .style(theme::Container::Box)
+    .into()
+}
+
+// The last failure and a suggested fix, with its causes on hover. Nothing blocks while it is shown.
+fn failure_banner(failure: &errors::Failure) -> Element<'_, Message> {
+    let mut title = failure.title.clone();
+    if failure.repeats > 1 {
+        title = format!("{} ({} times in a row)", title, failure.repeats);
+    }
+    let details = failure.chain.join("\n");
+
+    let text = Column::new()
+        .spacing(4)
+        .width(Length::Fill)
+        .push(
+            Text::new(title)
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY)
+                .style(theme::Text::Color(Color::from_rgb8(0xd0, 0x3b, 0x2f))),
+        )
+        .push(
+            Text::new(failure.message.clone())
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY),
+        )
+        .push_maybe(failure.fix.map(|fix| {
+            Text::new(format!("Try this: {}", fix))
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY)
+        }));
+
+    let dismiss_button = Button::new(
+        Text::new("Dismiss")
+            .font(MONOCRAFT)
+            .size(text_sizes::SECONDARY),
+    )
+    .padding(6)
+    .style(button_styles::MinecraftButton)
+    .on_press(Message::DismissFailurePressed);
+
+    Container::new(
+        Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(
+                Tooltip::new(
+                    text,
+                    Text::new(details)
+                        .font(MONOCRAFT)
+                        .size(text_sizes::SECONDARY),
+                    TooltipPosition::Bottom,
+                )
+                .style(theme::Container::Box),
+            )
+            .push(dismiss_button),
+    )
+    .padding([8, 20])
+    .into()
+}
+
+// Show a folder in the desktop's file manager.
+fn open_in_file_manager(path: &Path) -> io::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else {
+        "xdg-open"
+    };
+    std::process::Command::new(program).arg(path).spawn()?;
+    Ok(())
+}
+
+// Ask where to save an exported world. Bedrock worlds are offered as `.mcworld`.
+fn save_world_dialog(name: &str, bedrock: bool) -> Option<PathBuf> {
+    let (label, extension) = if bedrock {
+        ("Bedrock world", "mcworld")
+    } else {
+        ("Zip archive", "zip")
+    };
+    FileDialog::new()
+        .add_filter(label, &[extension])
+        .set_file_name(format!("{}.{}", name, extension))
+        .save_file()
+}
+
+fn load_icon() -> Result<Icon, image::ImageError> {
+    let icon_data = assets::get_asset("icon.ico").expect("Icon not found in assets");
+    let img = image::load_from_memory(&icon_data)?.to_rgba8();
+    let width = img.width();
+    let height = img.height();
+    let raw_data = img.into_raw();
+    Ok(window::icon::from_rgba(raw_data, width, height).unwrap())
+}
+
+/// Inputs and result of the chunk recovery view.
+#[derive(Default)]
+struct ChunkRecovery {
+    world: Option<PathBuf>,
+    dimension: region::Dimension,
+    x: String,
+    z: String,
+    /// Coordinates are block coordinates (as shown on F3) unless this is set.
+    chunk_coordinates: bool,
+    found: Option<region::RecoveredChunk>,
+    status: String,
+}
+
+impl ChunkRecovery {
+    fn chunk(&self) -> Option<region::ChunkPosition> {
+        let x = self.x.trim().parse().ok()?;
+        let z = self.z.trim().parse().ok()?;
+        Some(if self.chunk_coordinates {
+            region::ChunkPosition { x, z }
+        } else {
+            region::ChunkPosition::from_block(x, z)
+        })
+    }
+}
+
+/// Parts of a snapshot offered for a selective restore, and which ones are ticked.
+#[derive(Default)]
+struct PartialRestore {
+    snapshot_id: String,
+    parts: Vec<selective::RestorePart>,
+    selected: Vec<bool>,
+    /// File or folder typed in to add as a part of its own.
+    custom_path: String,
+    status: String,
+}
+
+/// Player picked in the inspector and their state in each snapshot, newest first.
+#[derive(Default)]
+struct PlayerInspector {
+    players: Vec<players::PlayerFile>,
+    player: Option<players::PlayerFile>,
+    history: Vec<players::PlayerState>,
+    /// Only snapshots holding an item that contains this are listed.
+    item_filter: String,
+    status: String,
+}
+
+#[derive(Default)]
+struct RustCraft {
+    config: Config,
+    active_schedule: bool,
+    image_path: String,
+    backup_thread: Option<Sender<()>>,
+    timer_text: String,
+    last_backup_time: Option<Instant>,
+    /// Last failure, shown above the current view until dismissed.
+    failure: Option<errors::Failure>,
+    /// Failures of scheduled backups, sent by the backup thread.
+    scheduled_failures: Option<Receiver<errors::Failure>>,
+    /// "Retry" clicked on a failure notification; every notification gets a sender.
+    retry_sender: Option<Sender<()>>,
+    retry_requests: Option<Receiver<()>>,
+    show_history: bool,
+    snapshots: Vec<String>,
+    /// Snapshots taken before a restore, marked as such in the history.
+    safety_snapshots: HashSet<String>,
+    history_status: String,
+    /// Snapshot being exported and the worlds in it, while the user picks one.
+    export_choice: Option<(String, Vec<String>)>,
+    show_recovery: bool,
+    recovery: ChunkRecovery,
+    partial_restore: Option<PartialRestore>,
+    show_players: bool,
+    inspector: PlayerInspector,
+    /// Recorded backup runs, newest first, while the run history is open.
+    runs: Option<Vec<runs::RunRecord>>,
+    /// Outcome of the last action in the run history, like sending test webhooks.
+    runs_status: String,
+    /// End of the newest log file, while the log viewer is open.
+    logs: Option<Vec<String>>,
+    /// Snapshots picked in the history for comparison, oldest first once two are picked.
+    compare_selection: Vec<String>,
+    /// Snapshots being compared and what changed between them.
+    snapshot_diff: Option<(String, String, diff::SnapshotDiff)>,
+    /// Map of the snapshot last clicked in the history, shown in place of the banner image.
+    preview: Option<ImageHandle>,
+    show_discovery: bool,
+    discovered_saves: Vec<discovery::DiscoveredSaves>,
+    discovery_status: String,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    MinecraftDirPressed,
+    BackupDirPressed,
+    ScheduleChanged(i32),
+    MinecraftDirectorySelected(Option<String>),
+    BackupDirectorySelected(Option<String>),
+    StartPressed,
+    BackupCompleted,
+    BackupError(String),
+    Tick(Instant),
+    ManualBackupFinished(Result<(), errors::Failure>),
+    FontLoaded(Result<(), font::Error>),
+    PreviewLoaded(String, Result<Option<Vec<u8>>, String>),
+    ToggleTheme,
+    HistoryPressed,
+    RestoreSnapshotPressed(String),
+    VerifySnapshotPressed(String),
+    DiscoverPressed,
+    DiscoveredSavesSelected(PathBuf),
+    ImportWorldPressed(PathBuf),
+    ExportWorldPressed(PathBuf),
+    ExportSnapshotPressed(String),
+    ExportSnapshotWorldPressed(String, String),
+    SnapshotSelected(String),
+    ComparePressed,
+    CloseDiffPressed,
+    RecoverChunkPressed,
+    RecoveryWorldPressed,
+    RecoveryDimensionSelected(region::Dimension),
+    RecoveryXChanged(String),
+    RecoveryZChanged(String),
+    RecoveryCoordinatesToggled,
+    RecoverySearchPressed,
+    RecoveryRestorePressed,
+    RestorePartsPressed(String),
+    RestorePartToggled(usize, bool),
+    RestorePartPathChanged(String),
+    RestorePartPathAdded,
+    RestoreSelectedPartsPressed,
+    CloseRestorePartsPressed,
+    PlayersPressed,
+    PlayerSelected(players::PlayerFile),
+    PlayerFilterChanged(String),
+    RestorePlayerPressed(String),
+    RunsPressed,
+    LogsPressed,
+    OpenLogsPressed,
+    DismissFailurePressed,
+    TestWebhooksPressed,
+    TestFinished(String),
+    TestEmailPressed,
+}
+
+impl RustCraft {
+    fn toggle_theme(&mut self) {
+        self.config.dark_theme = !self.config.dark_theme;
+    }
+
+    // Show a failure in the banner. The same failure again only bumps its count.
+    fn report_failure(&mut self, failure: errors::Failure) {
+        match &mut self.failure {
+            Some(shown) if shown.same_as(&failure) => shown.repeats += 1,
+            _ => self.failure = Some(failure),
+        }
+    }
+
+    fn save_config(&self) {
+        if let Err(e) = config::save_configuration(&self.config) {
+            error!("Error saving configuration: {}", e);
+        }
+    }
+
+    // Open the configured backup destination. Every backup, restore and
+    // verification goes through this, so the engine never touches `std::fs` directly.
+    fn open_backend(&self) -> io::Result<Box<dyn StorageBackend>> {
+        storage::backend::open_destination(&self.config).unwrap_or_else(|| {
+            Err(errors::known(
+                errors::Cause::NoDestination,
+                "No backup destination selected",
+            ))
+        })
+    }
+
+    fn has_source(&self) -> bool {
+        match self.config.source {
+            SourceKind::Local | SourceKind::Server => self.config.minecraft_directory.is_some(),
+            SourceKind::Sftp => true,
+        }
+    }
+
+    fn has_destination(&self) -> bool {
+        match self.config.destination {
+            DestinationKind::Local => self.config.backup_directory.is_some(),
+            DestinationKind::S3 | DestinationKind::Sftp | DestinationKind::WebDav => true,
+        }
+    }
+
+    fn refresh_snapshots(&mut self) {
+        match self.open_backend().and_then(|backend| {
+            Ok((
+                file_operations::list_snapshots(backend.as_ref())?,
+                file_operations::list_safety_snapshots(backend.as_ref())?,
+            ))
+        }) {
+            Ok((mut snapshots, safety_snapshots)) => {
+                snapshots.reverse(); // Newest first
+                self.snapshots = snapshots;
+                self.safety_snapshots = safety_snapshots.into_iter().collect();
+            }
+            Err(e) => {
+                self.snapshots.clear();
+                self.safety_snapshots.clear();
+                self.history_status = format!("Could not list backups: {}", e);
+            }
+        }
+    }
+
+    // List of snapshots in the backup destination, newest first, with per-snapshot actions.
+    fn history_view(&self) -> Element<'_, Message> {
+        let mut snapshot_list = Column::new().spacing(8).padding([0, 16, 0, 0]);
+
+        if self.snapshots.is_empty() {
+            snapshot_list = snapshot_list.push(
+                Text::new("No backups found")
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            );
+        }
+
+        for snapshot_id in &self.snapshots {
+            let row = Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(
+                    // Click snapshots to pick the two to compare
+                    Button::new(
+                        Text::new({
+                            let mut label = snapshot_id.clone();
+                            if self.safety_snapshots.contains(snapshot_id) {
+                                label.push_str(" (before a restore)");
+                            }
+                            if self.compare_selection.contains(snapshot_id) {
+                                label = format!("> {}", label);
+                            }
+                            label
+                        })
+                        .font(MONOCRAFT)
+                        .size(text_sizes::SECONDARY),
+                    )
+                    .padding(0)
+                    .width(Length::Fill)
+                    .style(button_styles::TransparentButton)
+                    .on_press(Message::SnapshotSelected(snapshot_id.clone())),
+                )
+                .push(
+                    Button::new(
+                        Text::new("Verify")
+                            .font(MONOCRAFT)
+                            .size(text_sizes::SECONDARY),
+                    )
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(Message::VerifySnapshotPressed(snapshot_id.clone())),
+                )
+                .push(
+                    Button::new(
+                        Text::new("Restore")
+                            .font(MONOCRAFT)
+                            .size(text_sizes::SECONDARY),
+                    )
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(Message::RestoreSnapshotPressed(snapshot_id.clone())),
+                )
+                .push(
+                    Button::new(
+                        Text::new("Parts")
+                            .font(MONOCRAFT)
+                            .size(text_sizes::SECONDARY),
+                    )
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(Message::RestorePartsPressed(snapshot_id.clone())),
+                )
+                .push(
+                    Button::new(
+                        Text::new("Export")
+                            .font(MONOCRAFT)
+                            .size(text_sizes::SECONDARY),
+                    )
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(Message::ExportSnapshotPressed(snapshot_id.clone())),
+                );
+            snapshot_list = snapshot_list.push(row);
+
+            // Worlds to choose from when exporting a snapshot that holds several
+            if let Some((_, worlds)) = self
+                .export_choice
+                .as_ref()
+                .filter(|(export_id, _)| export_id == snapshot_id)
+            {
+                for world in worlds {
+                    snapshot_list = snapshot_list.push(
+                        Row::new()
+                            .spacing(10)
+                            .align_items(Alignment::Center)
+                            .padding([0, 0, 0, 20])
+                            .push(
+                                Text::new(world.clone())
+                                    .font(MONOCRAFT)
+                                    .size(text_sizes::SECONDARY)
+                                    .width(Length::Fill),
+                            )
+                            .push(
+                                Button::new(
+                                    Text::new("Export world")
+                                        .font(MONOCRAFT)
+                                        .size(text_sizes::SECONDARY),
+                                )
+                                .padding(6)
+                                .style(button_styles::MinecraftButton)
+                                .on_press(
+                                    Message::ExportSnapshotWorldPressed(
+                                        snapshot_id.clone(),
+                                        world.clone(),
+                                    ),
+                                ),
+                            ),
+                    );
+                }
+            }
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::HistoryPressed);
+
+        let recover_button = Button::new(Text::new("Recover Chunk").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::RecoverChunkPressed);
+
+        let players_button = Button::new(Text::new("Players").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::PlayersPressed);
+
+        let runs_button = Button::new(Text::new("Runs").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::RunsPressed);
+
+        let mut compare_button = Button::new(Text::new("Compare").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if self.compare_selection.len() == 2 {
+            compare_button = compare_button.on_press(Message::ComparePressed);
+        }
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Backup History").font(MONOCRAFT))
+            .push_maybe(self.config.encryption.enabled.then(encryption_warning))
+            .push(Scrollable::new(snapshot_list).height(Length::Fixed(280f32)))
+            .push(
+                Text::new(self.history_status.clone())
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            )
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(compare_button)
+                    .push(recover_button)
+                    .push(players_button)
+                    .push(runs_button)
+                    .push(back_button),
+            )
+            .into()
+    }
+
+    // What changed between the two snapshots picked in the history.
+    fn diff_view<'a>(
+        &self,
+        old_id: &'a str,
+        new_id: &'a str,
+        diff: &'a diff::SnapshotDiff,
+    ) -> Element<'a, Message> {
+        // Large worlds change thousands of files; only the first ones of each kind are listed
+        const SHOWN: usize = 50;
+        let regions: Vec<String> = diff
+            .regions
+            .iter()
+            .map(|(path, chunks)| format!("{}: {} chunks changed", path, chunks))
+            .collect();
+        let sections: [(&str, &[String]); 6] = [
+            ("level.dat", &diff.level),
+            ("Regions", &regions),
+            ("Players", &diff.players),
+            ("Added", &diff.added),
+            ("Removed", &diff.removed),
+            ("Modified", &diff.modified),
+        ];
+
+        let mut changes = Column::new().spacing(4).padding([0, 16, 0, 0]);
+        for (title, lines) in sections.into_iter().filter(|(_, lines)| !lines.is_empty()) {
+            changes = changes.push(
+                Text::new(format!("{} ({})", title, lines.len()))
+                    .font(MONOCRAFT)
+                    .size(text_sizes::PRIMARY),
+            );
+            for line in lines.iter().take(SHOWN) {
+                changes = changes.push(
+                    Text::new(line.clone())
+                        .font(MONOCRAFT)
+                        .size(text_sizes::SECONDARY),
+                );
+            }
+            if lines.len() > SHOWN {
+                changes = changes.push(
+                    Text::new(format!("... and {} more", lines.len() - SHOWN))
+                        .font(MONOCRAFT)
+                        .size(text_sizes::SECONDARY),
+                );
+            }
+        }
+        if diff.added.is_empty() && diff.removed.is_empty() && diff.modified.is_empty() {
+            changes = changes.push(
+                Text::new("The snapshots are identical")
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            );
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::CloseDiffPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(
+                Text::new(format!("{} -> {}", old_id, new_id))
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            )
+            .push(Scrollable::new(changes).height(Length::Fixed(320f32)))
+            .push(back_button)
+            .into()
+    }
+
+    // Pick dimensions, players, datapacks or single files of a snapshot to restore.
+    fn parts_view<'a>(&self, partial: &'a PartialRestore) -> Element<'a, Message> {
+        let small_text = |text: String| Text::new(text).font(MONOCRAFT).size(text_sizes::SECONDARY);
+
+        let mut parts_list = Column::new().spacing(8).padding([0, 16, 0, 0]);
+        if partial.parts.is_empty() {
+            parts_list = parts_list.push(small_text("No parts found".to_string()));
+        }
+        for (index, part) in partial.parts.iter().enumerate() {
+            parts_list = parts_list.push(
+                Checkbox::new(part.label.clone(), partial.selected[index])
+                    .on_toggle(move |checked| Message::RestorePartToggled(index, checked))
+                    .font(MONOCRAFT)
+                    .text_size(text_sizes::SECONDARY),
+            );
+        }
+
+        let custom_path = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(
+                TextInput::new(
+                    "File or folder, like world/data/raids.dat",
+                    &partial.custom_path,
+                )
+                .on_input(Message::RestorePartPathChanged)
+                .on_submit(Message::RestorePartPathAdded)
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY),
+            )
+            .push(
+                Button::new(small_text("Add".to_string()))
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(Message::RestorePartPathAdded),
+            );
+
+        let mut restore_button = Button::new(Text::new("Restore Selected").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if partial.selected.contains(&true) {
+            restore_button = restore_button.on_press(Message::RestoreSelectedPartsPressed);
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::CloseRestorePartsPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new(format!("Restore parts of {}", partial.snapshot_id)).font(MONOCRAFT))
+            .push(Scrollable::new(parts_list).height(Length::Fixed(220f32)))
+            .push(custom_path)
+            .push(small_text(partial.status.clone()))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(restore_button)
+                    .push(back_button),
+            )
+            .into()
+    }
+
+    // Every recorded backup run, failed ones included, newest first.
+    fn runs_view<'a>(&self, runs: &'a [runs::RunRecord]) -> Element<'a, Message> {
+        let small_text = |text: String| Text::new(text).font(MONOCRAFT).size(text_sizes::SECONDARY);
+
+        let mut runs_list = Column::new().spacing(8).padding([0, 16, 0, 0]);
+        if runs.is_empty() {
+            runs_list = runs_list.push(small_text("No backups have run yet".to_string()));
+        }
+        // A failure that repeats every run is listed once, with how often it happened
+        let mut grouped: Vec<(&runs::RunRecord, usize)> = Vec::new();
+        for run in runs {
+            match grouped.last_mut() {
+                Some((last, repeats)) if run.error.is_some() && last.error == run.error => {
+                    *repeats += 1
+                }
+                _ => grouped.push((run, 1)),
+            }
+        }
+        for (run, repeats) in grouped {
+            let details = std::iter::once(format!("{} -> {}", run.source, run.destination))
+                .chain(run.snapshot_id.iter().map(|id| format!("Snapshot {}", id)))
+                .chain(run.error_chain.iter().cloned())
+                .chain(run.warnings.iter().cloned())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut summary = run.summary();
+            if repeats > 1 {
+                summary = format!("{} ({} times in a row)", summary, repeats);
+            }
+            runs_list = runs_list.push(
+                Tooltip::new(
+                    small_text(summary),
+                    small_text(details),
+                    TooltipPosition::Bottom,
+                )
+                .style(theme::Container::Box),
+            );
+        }
+
+        let logs_button = Button::new(Text::new("Logs").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::LogsPressed);
+
+        let mut webhooks_button = Button::new(Text::new("Test Webhooks").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if !self.config.webhooks.is_empty() {
+            webhooks_button = webhooks_button.on_press(Message::TestWebhooksPressed);
+        }
+
+        let mut email_button = Button::new(Text::new("Test Email").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if !self.config.email.smtp.host.is_empty() {
+            email_button = email_button.on_press(Message::TestEmailPressed);
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::RunsPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Backup Runs").font(MONOCRAFT))
+            .push(Scrollable::new(runs_list).height(Length::Fixed(280f32)))
+            .push(small_text(self.runs_status.clone()))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(webhooks_button)
+                    .push(email_button)
+                    .push(logs_button)
+                    .push(back_button),
+            )
+            .into()
+    }
+
+    // The end of today's log file, for when a backup failed and the reason is not obvious.
+    fn logs_view<'a>(&self, lines: &'a [String]) -> Element<'a, Message> {
+        let mut log = Column::new().spacing(2).padding([0, 16, 0, 0]);
+        if lines.is_empty() {
+            log = log.push(
+                Text::new("Nothing has been logged yet")
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            );
+        }
+        for line in lines {
+            log = log.push(Text::new(line.clone()).size(text_sizes::SECONDARY));
+        }
+
+        let open_button = Button::new(Text::new("Open Logs").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::OpenLogsPressed);
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::LogsPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Log").font(MONOCRAFT))
+            .push(
+                Scrollable::new(log)
+                    .height(Length::Fixed(320f32))
+                    .direction(scrollable::Direction::Both {
+                        vertical: scrollable::Properties::default(),
+                        horizontal: scrollable::Properties::default(),
+                    }),
+            )
+            .push(Row::new().spacing(10).push(open_button).push(back_button))
+            .into()
+    }
+
+    // A player's inventory, ender chest, position and level in every snapshot that has them.
+    fn players_view(&self) -> Element<'_, Message> {
+        let small_text = |text: String| Text::new(text).font(MONOCRAFT).size(text_sizes::SECONDARY);
+        let inspector = &self.inspector;
+
+        let picker = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(
+                PickList::new(
+                    &inspector.players[..],
+                    inspector.player.clone(),
+                    Message::PlayerSelected,
+                )
+                .placeholder("Select a player")
+                .font(MONOCRAFT)
+                .text_size(text_sizes::SECONDARY),
+            )
+            .push(
+                TextInput::new("Item, like diamond", &inspector.item_filter)
+                    .on_input(Message::PlayerFilterChanged)
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            );
+
+        let mut history = Column::new().spacing(8).padding([0, 16, 0, 0]);
+        let shown = inspector.history.iter().filter(|state| {
+            inspector.item_filter.trim().is_empty() || state.has_item(&inspector.item_filter)
+        });
+        for state in shown {
+            history = history
+                .push(
+                    Row::new()
+                        .spacing(10)
+                        .align_items(Alignment::Center)
+                        .push(
+                            Text::new(format!("{}: {}", state.snapshot_id, state.summary()))
+                                .font(MONOCRAFT)
+                                .size(text_sizes::SECONDARY)
+                                .width(Length::Fill),
+                        )
+                        .push(
+                            Button::new(small_text("Restore".to_string()))
+                                .padding(6)
+                                .style(button_styles::MinecraftButton)
+                                .on_press(Message::RestorePlayerPressed(state.snapshot_id.clone())),
+                        ),
+                )
+                .push(small_text(format!(
+                    "Inventory: {}",
+                    state.inventory.join(", ")
+                )))
+                .push(small_text(format!(
+                    "Ender chest: {}",
+                    state.ender_items.join(", ")
+                )));
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::PlayersPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Players").font(MONOCRAFT))
+            .push(picker)
+            .push(Scrollable::new(history).height(Length::Fixed(260f32)))
+            .push(small_text(inspector.status.clone()))
+            .push(back_button)
+            .into()
+    }
+
+    // Pick a chunk of a live world and bring back its newest good copy from the history.
+    fn recovery_view(&self) -> Element<'_, Message> {
+        let small_text = |text: String| Text::new(text).font(MONOCRAFT).size(text_sizes::SECONDARY);
+
+        let world_button = Button::new(
+            Text::new("Select World")
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY),
+        )
+        .padding(6)
+        .style(button_styles::MinecraftButton)
+        .on_press(Message::RecoveryWorldPressed);
+        let world_text = small_text(match &self.recovery.world {
+            Some(world) => truncate_path(&world.to_string_lossy()),
+            None => "No world selected".to_string(),
+        });
+
+        let coordinates_button = Button::new(small_text(
+            if self.recovery.chunk_coordinates {
+                "Chunk"
+            } else {
+                "Block"
+            }
+            .to_string(),
+        ))
+        .padding(6)
+        .style(button_styles::MinecraftButton)
+        .on_press(Message::RecoveryCoordinatesToggled);
+
+        let coordinates = Row::new()
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .push(coordinates_button)
+            .push(
+                TextInput::new("X", &self.recovery.x)
+                    .on_input(Message::RecoveryXChanged)
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY)
+                    .width(Length::Fixed(90f32)),
+            )
+            .push(
+                TextInput::new("Z", &self.recovery.z)
+                    .on_input(Message::RecoveryZChanged)
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY)
+                    .width(Length::Fixed(90f32)),
+            )
+            .push(
+                PickList::new(
+                    &region::Dimension::ALL[..],
+                    Some(self.recovery.dimension),
+                    Message::RecoveryDimensionSelected,
+                )
+                .font(MONOCRAFT)
+                .text_size(text_sizes::SECONDARY),
+            );
+
+        let mut search_button = Button::new(Text::new("Search").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if self.recovery.world.is_some() && self.recovery.chunk().is_some() {
+            search_button = search_button.on_press(Message::RecoverySearchPressed);
+        }
+
+        let mut restore_button = Button::new(Text::new("Restore Chunk").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+        if self.recovery.found.is_some() {
+            restore_button = restore_button.on_press(Message::RecoveryRestorePressed);
+        }
+
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::RecoverChunkPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Recover a Chunk").font(MONOCRAFT))
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .align_items(Alignment::Center)
+                    .push(world_button)
+                    .push(world_text),
+            )
+            .push(coordinates)
+            .push(
+                Row::new()
+                    .spacing(10)
+                    .push(search_button)
+                    .push(restore_button),
+            )
+            .push(small_text(self.recovery.status.clone()))
+            .push(back_button)
+            .into()
+    }
+
+    // Saves folders found in the known launcher layouts, each usable as the source in one click.
+    fn discovery_view(&self) -> Element<'_, Message> {
+        let mut saves_list = Column::new().spacing(8).padding([0, 16, 0, 0]);
+
+        if self.discovered_saves.is_empty() {
+            saves_list = saves_list.push(
+                Text::new("No saves folders found")
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            );
+        }
+
+        for saves in &self.discovered_saves {
+            let label = Tooltip::new(
+                Text::new(format!("{} - {}", saves.launcher, saves.name))
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+                Text::new(
+                    std::iter::once(saves.path.display().to_string())
+                        .chain(saves.worlds.iter().cloned())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )
+                .font(MONOCRAFT)
+                .size(text_sizes::SECONDARY),
+                TooltipPosition::Bottom,
+            )
+            .style(theme::Container::Box);
 
-#[derive(Default)]
-struct RustCraft {
-    schedule_hours: i32,
-    minecraft_directory: Option<String>,
-    backup_directory: Option<String>,
-    active_schedule: bool,
-    image_path: String,
-    backup_thread: Option<Sender<()>>,
-    timer_text: String,
-    last_backup_time: Option<Instant>,
-    dark_theme: bool,
-}
+            let action = |label: &'static str, message: Message| {
+                Button::new(Text::new(label).font(MONOCRAFT).size(text_sizes::SECONDARY))
+                    .padding(6)
+                    .style(button_styles::MinecraftButton)
+                    .on_press(message)
+            };
 
-#[derive(Debug, Clone)]
-enum Message {
-    MinecraftDirPressed,
-    BackupDirPressed,
-    ScheduleChanged(i32),
-    MinecraftDirectorySelected(Option<String>),
-    BackupDirectorySelected(Option<String>),
-    StartPressed,
-    BackupCompleted,
-    BackupError(String),
-    Tick(Instant),
-    FontLoaded(Result<(), font::Error>),
-    ToggleTheme,
-}
+            let row = Row::new()
+                .spacing(10)
+                .align_items(Alignment::Center)
+                .push(Container::new(label).width(Length::Fill))
+                .push(action(
+                    "Use",
+                    Message::DiscoveredSavesSelected(saves.path.clone()),
+                ))
+                .push(action(
+                    "Import",
+                    Message::ImportWorldPressed(saves.path.clone()),
+                ))
+                .push(action(
+                    "Export",
+                    Message::ExportWorldPressed(saves.path.clone()),
+                ));
+            saves_list = saves_list.push(row);
+        }
 
-impl RustCraft {
-    fn toggle_theme(&mut self) {
-        self.dark_theme = !self.dark_theme;
+        let back_button = Button::new(Text::new("Back").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton)
+            .on_press(Message::DiscoverPressed);
+
+        Column::new()
+            .align_items(Alignment::Center)
+            .spacing(20)
+            .padding(20)
+            .push(Text::new("Launcher Saves").font(MONOCRAFT))
+            .push(Scrollable::new(saves_list).height(Length::Fixed(280f32)))
+            .push(
+                Text::new(self.discovery_status.clone())
+                    .font(MONOCRAFT)
+                    .size(text_sizes::SECONDARY),
+            )
+            .push(back_button)
+            .into()
     }
 
     fn update_image_path(&mut self, message: Message) {
@@ -165,16 +1445,38 @@ impl RustCraft {
             _ => self.image_path.clone(),
         };
     }
-    fn get_minecraft_default_path() -> Option<PathBuf> {
-        dirs::home_dir().map(|path| path.join("AppData\\Roaming\\.minecraft\\saves"))
+
+    fn retry_sender(&self) -> Sender<()> {
+        self.retry_sender
+            .clone()
+            .expect("the retry channel is set up when the app starts")
+    }
+
+    // Back up once, right away, off the window: it may first wait for a scheduled backup to
+    // finish. The outcome comes back as `ManualBackupFinished`.
+    fn run_backup_now(&mut self, backend: Box<dyn StorageBackend>) -> Command<Message> {
+        let config = self.config.clone();
+        let retry = self.retry_sender();
+        Command::perform(
+            blocking(move || {
+                let result = run_backup_recorded(&config, backend.as_ref(), runs::Trigger::Manual);
+                notify_run(&config, &result, None, retry);
+                result
+                    .map(|_| ())
+                    .map_err(|e| errors::Failure::new("Backup failed", &e))
+            }),
+            Message::ManualBackupFinished,
+        )
     }
 
-    fn start_backup_thread(&mut self, hours: i32) {
+    fn start_backup_thread(&mut self, hours: i32, backend: Box<dyn StorageBackend>) {
         self.last_backup_time = Some(Instant::now());
 
         let (tx, rx): (Sender<()>, Receiver<()>) = mpsc::channel();
-        let src_dir = self.minecraft_directory.clone().unwrap();
-        let dst_dir = self.backup_directory.clone().unwrap();
+        let (failure_tx, failure_rx) = mpsc::channel();
+        self.scheduled_failures = Some(failure_rx);
+        let config = self.config.clone();
+        let retry = self.retry_sender();
         thread::spawn(move || loop {
             match rx.try_recv() {
                 Ok(_) | Err(mpsc::TryRecvError::Disconnected) => {
@@ -183,12 +1485,11 @@ impl RustCraft {
                 Err(mpsc::TryRecvError::Empty) => {}
             }
 
-            if let Err(e) =
-                file_operations::copy_directory(Path::new(&src_dir), Path::new(&dst_dir))
-            {
-                show_system_modal_message("Backup Error", &e.to_string());
-            } else {
-                notification::trigger_notification(true, None);
+            let result = run_backup_recorded(&config, backend.as_ref(), runs::Trigger::Scheduled);
+            let next = Local::now() + chrono::Duration::hours(hours.into());
+            notify_run(&config, &result, Some(next), retry.clone());
+            if let Err(e) = result {
+                let _ = failure_tx.send(errors::Failure::new("Scheduled backup failed", &e));
             }
             thread::sleep(Duration::from_secs((hours * 3600) as u64)); // Convert hours to seconds
         });
@@ -203,15 +1504,13 @@ impl Application for RustCraft {
     type Flags = ();
 
     fn new(_flags: ()) -> (Self, Command<Self::Message>) {
-        let (minecraft_directory, backup_directory, schedule_hours, dark_theme) =
-            config::load_configuration();
+        let (retry_sender, retry_requests) = mpsc::channel();
         (
             Self {
-                minecraft_directory,
-                backup_directory,
-                schedule_hours,
-                dark_theme,
+                config: config::load_configuration(),
                 image_path: "normal.png".to_string(),
+                retry_sender: Some(retry_sender),
+                retry_requests: Some(retry_requests),
                 ..Self::default()
             },
             Command::batch(vec![font::load(
@@ -228,15 +1527,39 @@ impl Application for RustCraft {
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
             Message::Tick(now) => {
+                let failures: Vec<errors::Failure> = self
+                    .scheduled_failures
+                    .as_ref()
+                    .map(|failures| failures.try_iter().collect())
+                    .unwrap_or_default();
+                for failure in failures {
+                    self.report_failure(failure);
+                }
+                let retries = self
+                    .retry_requests
+                    .as_ref()
+                    .map_or(0, |requests| requests.try_iter().count());
+                let mut command = Command::none();
+                if retries > 0 {
+                    info!("Retrying the backup from a notification");
+                    match self.open_backend() {
+                        Ok(backend) => command = self.run_backup_now(backend),
+                        Err(e) => self.report_failure(errors::Failure::new("Backup failed", &e)),
+                    }
+                }
+                if !self.active_schedule {
+                    return command;
+                }
                 if let Some(last_backup_time) = self.last_backup_time {
                     let elapsed = now.duration_since(last_backup_time);
                     let seconds_since_last_backup = elapsed.as_secs();
-                    let total_seconds_for_backup = (self.schedule_hours * 3600) as u64;
+                    let total_seconds_for_backup = (self.config.backup_frequency * 3600) as u64;
 
                     if seconds_since_last_backup >= total_seconds_for_backup {
                         // Reset the timer if the scheduled time has elapsed
                         self.last_backup_time = Some(now);
-                        self.timer_text = format!("{:02}:{:02}:{:02}", self.schedule_hours, 0, 0);
+                        self.timer_text =
+                            format!("{:02}:{:02}:{:02}", self.config.backup_frequency, 0, 0);
                     } else {
                         let seconds_remaining =
                             total_seconds_for_backup - seconds_since_last_backup;
@@ -248,16 +1571,32 @@ impl Application for RustCraft {
                 } else {
                     // Initialize the timer if it hasn't been set
                     self.last_backup_time = Some(now);
-                    self.timer_text = format!("{:02}:{:02}:{:02}", self.schedule_hours, 0, 0);
+                    self.timer_text =
+                        format!("{:02}:{:02}:{:02}", self.config.backup_frequency, 0, 0);
+                }
+                command
+            }
+            Message::ManualBackupFinished(result) => {
+                match result {
+                    Ok(()) => self.update_image_path(Message::BackupCompleted),
+                    Err(failure) => {
+                        let error_message = format!("Backup failed: {}", failure.message);
+                        self.update_image_path(Message::BackupError(error_message));
+                        self.report_failure(failure);
+                    }
+                }
+                if self.show_history {
+                    self.refresh_snapshots();
                 }
                 Command::none()
             }
             Message::MinecraftDirPressed => {
                 let initial_directory = self
+                    .config
                     .minecraft_directory
                     .clone()
                     .map(PathBuf::from)
-                    .or_else(RustCraft::get_minecraft_default_path);
+                    .or_else(discovery::vanilla_saves_path);
                 let path = FileDialog::new()
                     .set_directory(initial_directory.unwrap_or_else(|| PathBuf::from(".")))
                     .pick_folder();
@@ -272,7 +1611,7 @@ impl Application for RustCraft {
                 )
             }
             Message::ScheduleChanged(hours) => {
-                self.schedule_hours = hours;
+                self.config.backup_frequency = hours;
 
                 // If hours is 0 and there is an active schedule, send a signal to stop the backup thread and deactivate the schedule.
                 if hours == 0 && self.active_schedule {
@@ -282,19 +1621,13 @@ impl Application for RustCraft {
                     }
                 }
 
-                if let Err(e) = config::save_configuration(
-                    &self.minecraft_directory,
-                    &self.backup_directory,
-                    self.schedule_hours,
-                    self.dark_theme,
-                ) {
-                    println!("Error saving configuration: {}", e);
-                }
+                self.save_config();
                 Command::none()
             }
             Message::BackupDirPressed => {
                 // Check if a backup directory is already specified, otherwise default to the desktop directory
                 let initial_directory = self
+                    .config
                     .backup_directory
                     .clone()
                     .map(PathBuf::from)
@@ -320,27 +1653,26 @@ impl Application for RustCraft {
                     }
                     self.active_schedule = false;
                     self.update_image_path(Message::BackupCompleted);
-                } else if self.schedule_hours == 0 {
-                    // Perform an immediate backup without threading
-                    let src_dir = self.minecraft_directory.clone().unwrap();
-                    let dst_dir = self.backup_directory.clone().unwrap();
-                    match file_operations::copy_directory(Path::new(&src_dir), Path::new(&dst_dir))
-                    {
-                        Ok(_) => {
-                            self.update_image_path(Message::BackupCompleted);
-                            notification::trigger_notification(true, None);
-                        }
-                        Err(e) => {
-                            let error_message = format!("Backup failed: {}", e);
-                            self.update_image_path(Message::BackupError(error_message.clone()));
-                            notification::trigger_notification(false, Some(&error_message));
-                        }
+                    return Command::none();
+                }
+
+                let backend = match self.open_backend() {
+                    Ok(backend) => backend,
+                    Err(e) => {
+                        let error_message = format!("Backup failed: {}", e);
+                        self.update_image_path(Message::BackupError(error_message));
+                        self.report_failure(errors::Failure::new("Backup failed", &e));
+                        return Command::none();
                     }
-                } else {
-                    self.start_backup_thread(self.schedule_hours);
-                    self.active_schedule = true;
-                    self.update_image_path(Message::StartPressed);
+                };
+
+                if self.config.backup_frequency == 0 {
+                    // Perform an immediate backup, without a schedule
+                    return self.run_backup_now(backend);
                 }
+                self.start_backup_thread(self.config.backup_frequency, backend);
+                self.active_schedule = true;
+                self.update_image_path(Message::StartPressed);
                 Command::none()
             }
 
@@ -352,49 +1684,656 @@ impl Application for RustCraft {
             Message::BackupError(err_msg) => {
                 self.active_schedule = false;
                 self.update_image_path(Message::BackupError("assets/error.png".to_string()));
-                show_system_modal_message("Backup Error", &err_msg);
+                self.report_failure(errors::Failure::new(
+                    "Backup failed",
+                    &io::Error::other(err_msg),
+                ));
                 self.image_path = "assets/error.png".to_string();
                 Command::none()
             }
             Message::MinecraftDirectorySelected(path) => {
-                self.minecraft_directory = path;
-                config::save_configuration(
-                    &self.minecraft_directory,
-                    &self.backup_directory,
-                    self.schedule_hours,
-                    self.dark_theme,
-                )
-                .unwrap();
-                println!(
+                self.config.minecraft_directory = path;
+                self.save_config();
+                info!(
                     "Selected Minecraft directory: {:?}",
-                    self.minecraft_directory
+                    self.config.minecraft_directory
                 );
                 Command::none()
             }
             Message::BackupDirectorySelected(path) => {
-                self.backup_directory = path;
-                config::save_configuration(
-                    &self.minecraft_directory,
-                    &self.backup_directory,
-                    self.schedule_hours,
-                    self.dark_theme,
+                self.config.backup_directory = path;
+                self.save_config();
+                info!(
+                    "Selected Backup directory: {:?}",
+                    self.config.backup_directory
+                );
+                Command::none()
+            }
+
+            Message::HistoryPressed => {
+                self.show_history = !self.show_history;
+                self.history_status.clear();
+                self.export_choice = None;
+                self.show_recovery = false;
+                self.partial_restore = None;
+                self.show_players = false;
+                self.runs = None;
+                self.logs = None;
+                self.compare_selection.clear();
+                self.snapshot_diff = None;
+                self.preview = None;
+                if self.show_history {
+                    self.refresh_snapshots();
+                }
+                Command::none()
+            }
+            Message::RestoreSnapshotPressed(snapshot_id) => {
+                // Restore into a folder of the user's choosing, defaulting to the Minecraft directory
+                let initial_directory = self
+                    .config
+                    .minecraft_directory
+                    .clone()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let Some(target) = FileDialog::new()
+                    .set_directory(initial_directory)
+                    .pick_folder()
+                else {
+                    return Command::none();
+                };
+
+                match self.open_backend().and_then(|backend| {
+                    file_operations::restore_snapshot(backend.as_ref(), &snapshot_id, &target)
+                }) {
+                    Ok(()) => {
+                        self.history_status =
+                            format!("Restored {} to {}", snapshot_id, target.display());
+                    }
+                    Err(e) => {
+                        self.history_status = format!("Restore failed: {}", e);
+                        self.report_failure(errors::Failure::new("Restore failed", &e));
+                    }
+                }
+                Command::none()
+            }
+            Message::VerifySnapshotPressed(snapshot_id) => {
+                // Files that match the manifest can still hold chunks that were already damaged
+                self.history_status = match self.open_backend().and_then(|backend| {
+                    let report = file_operations::verify_snapshot(backend.as_ref(), &snapshot_id)?;
+                    let regions = region::check_snapshot(backend.as_ref(), &snapshot_id)?;
+                    Ok((report, regions))
+                }) {
+                    Ok((report, regions)) if report.problems.is_empty() => {
+                        format!(
+                            "{}: {} files OK. {}",
+                            snapshot_id,
+                            report.checked,
+                            regions.summary()
+                        )
+                    }
+                    Ok((report, _)) => format!(
+                        "{}: {} of {} files damaged ({})",
+                        snapshot_id,
+                        report.problems.len(),
+                        report.checked,
+                        report.problems.join(", ")
+                    ),
+                    Err(e) => format!("Verification failed: {}", e),
+                };
+                Command::none()
+            }
+
+            Message::DiscoverPressed => {
+                self.show_discovery = !self.show_discovery;
+                self.discovery_status.clear();
+                if self.show_discovery {
+                    self.discovered_saves = discovery::discover_saves();
+                }
+                Command::none()
+            }
+            Message::DiscoveredSavesSelected(path) => {
+                self.show_discovery = false;
+                Command::perform(
+                    async move {
+                        Message::MinecraftDirectorySelected(Some(
+                            path.to_string_lossy().into_owned(),
+                        ))
+                    },
+                    |p| p,
+                )
+            }
+            Message::ImportWorldPressed(saves) => {
+                let Some(file) = FileDialog::new()
+                    .add_filter("World", &["zip", "mcworld"])
+                    .pick_file()
+                else {
+                    return Command::none();
+                };
+                self.discovery_status = match packages::import_world(&file, &saves) {
+                    Ok(world) => format!("Imported {}", world.display()),
+                    Err(e) => format!("Import failed: {}", e),
+                };
+                self.discovered_saves = discovery::discover_saves();
+                Command::none()
+            }
+            Message::ExportWorldPressed(saves) => {
+                let Some(world) = FileDialog::new()
+                    .set_title("Select the world to export")
+                    .set_directory(&saves)
+                    .pick_folder()
+                else {
+                    return Command::none();
+                };
+                if !world.join("level.dat").is_file() {
+                    self.discovery_status = format!("{} is not a world", world.display());
+                    return Command::none();
+                }
+                let name = if bedrock::is_world(&world) {
+                    bedrock::read_world(&world).name
+                } else {
+                    world
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                };
+                let Some(destination) = save_world_dialog(&name, bedrock::is_world(&world)) else {
+                    return Command::none();
+                };
+                self.discovery_status = match packages::export_world(&world, &destination) {
+                    Ok(()) => format!("Exported {} to {}", name, destination.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+                Command::none()
+            }
+            Message::ExportSnapshotPressed(snapshot_id) => {
+                match self
+                    .open_backend()
+                    .and_then(|backend| packages::snapshot_worlds(backend.as_ref(), &snapshot_id))
+                {
+                    Ok(worlds) if worlds.is_empty() => {
+                        self.history_status = format!("{} contains no worlds", snapshot_id);
+                    }
+                    // A single world needs no choosing
+                    Ok(worlds) if worlds.len() == 1 => {
+                        return self.update(Message::ExportSnapshotWorldPressed(
+                            snapshot_id,
+                            worlds[0].clone(),
+                        ));
+                    }
+                    Ok(worlds) => self.export_choice = Some((snapshot_id, worlds)),
+                    Err(e) => self.history_status = format!("Export failed: {}", e),
+                }
+                Command::none()
+            }
+            Message::ExportSnapshotWorldPressed(snapshot_id, world) => {
+                self.export_choice = None;
+                let backend = match self.open_backend() {
+                    Ok(backend) => backend,
+                    Err(e) => {
+                        self.history_status = format!("Export failed: {}", e);
+                        return Command::none();
+                    }
+                };
+                let bedrock =
+                    packages::snapshot_world_is_bedrock(backend.as_ref(), &snapshot_id, &world)
+                        .unwrap_or(false);
+                let name = world.rsplit('/').next().unwrap_or_default();
+                let name = if name.is_empty() { "world" } else { name };
+                let Some(destination) =
+                    save_world_dialog(&format!("{} {}", name, snapshot_id), bedrock)
+                else {
+                    return Command::none();
+                };
+                self.history_status = match packages::export_snapshot_world(
+                    backend.as_ref(),
+                    &snapshot_id,
+                    &world,
+                    &destination,
+                ) {
+                    Ok(()) => format!("Exported {} to {}", name, destination.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                };
+                Command::none()
+            }
+            Message::SnapshotSelected(snapshot_id) => {
+                if let Some(index) = self
+                    .compare_selection
+                    .iter()
+                    .position(|selected| *selected == snapshot_id)
+                {
+                    self.compare_selection.remove(index);
+                } else {
+                    // Picking a third snapshot replaces the oldest pick
+                    if self.compare_selection.len() == 2 {
+                        self.compare_selection.remove(0);
+                    }
+                    self.compare_selection.push(snapshot_id.clone());
+                }
+                // Rendering downloads every region file of the world, so it happens off the window
+                self.preview = None;
+                let backend = match self.open_backend() {
+                    Ok(backend) => backend,
+                    Err(e) => {
+                        self.history_status = format!("Could not render the map: {}", e);
+                        return Command::none();
+                    }
+                };
+                Command::perform(
+                    blocking(move || {
+                        let png = map::load_or_render(backend.as_ref(), &snapshot_id)
+                            .map_err(|e| e.to_string());
+                        (snapshot_id, png)
+                    }),
+                    |(snapshot_id, png)| Message::PreviewLoaded(snapshot_id, png),
+                )
+            }
+            Message::PreviewLoaded(snapshot_id, png) => {
+                // Only the most recently picked snapshot's map is shown
+                if self.compare_selection.last() == Some(&snapshot_id) {
+                    match png {
+                        Ok(png) => self.preview = png.map(ImageHandle::from_memory),
+                        Err(e) => self.history_status = format!("Could not render the map: {}", e),
+                    }
+                }
+                Command::none()
+            }
+            Message::ComparePressed => {
+                // The history is newest first, so the snapshot further down is the older one
+                let mut pair = self.compare_selection.clone();
+                pair.sort_by_key(|id| {
+                    std::cmp::Reverse(self.snapshots.iter().position(|snapshot| snapshot == id))
+                });
+                let [old_id, new_id] = <[String; 2]>::try_from(pair).unwrap_or_default();
+                match self
+                    .open_backend()
+                    .and_then(|backend| diff::diff_snapshots(backend.as_ref(), &old_id, &new_id))
+                {
+                    Ok(diff) => self.snapshot_diff = Some((old_id, new_id, diff)),
+                    Err(e) => self.history_status = format!("Comparison failed: {}", e),
+                }
+                Command::none()
+            }
+            Message::CloseDiffPressed => {
+                self.snapshot_diff = None;
+                Command::none()
+            }
+            Message::RecoverChunkPressed => {
+                self.show_recovery = !self.show_recovery;
+                self.recovery.status.clear();
+                Command::none()
+            }
+            Message::RecoveryWorldPressed => {
+                let initial_directory = self
+                    .config
+                    .minecraft_directory
+                    .clone()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("."));
+                if let Some(world) = FileDialog::new()
+                    .set_title("Select the damaged world")
+                    .set_directory(initial_directory)
+                    .pick_folder()
+                {
+                    self.recovery.world = Some(world);
+                    self.recovery.found = None;
+                }
+                Command::none()
+            }
+            Message::RecoveryDimensionSelected(dimension) => {
+                self.recovery.dimension = dimension;
+                self.recovery.found = None;
+                Command::none()
+            }
+            Message::RecoveryXChanged(x) => {
+                self.recovery.x = x;
+                self.recovery.found = None;
+                Command::none()
+            }
+            Message::RecoveryZChanged(z) => {
+                self.recovery.z = z;
+                self.recovery.found = None;
+                Command::none()
+            }
+            Message::RecoveryCoordinatesToggled => {
+                self.recovery.chunk_coordinates = !self.recovery.chunk_coordinates;
+                self.recovery.found = None;
+                Command::none()
+            }
+            Message::RecoverySearchPressed => {
+                let (Some(world), Some(chunk)) = (&self.recovery.world, self.recovery.chunk())
+                else {
+                    return Command::none();
+                };
+                let found = self.open_backend().and_then(|backend| {
+                    region::find_good_chunk(
+                        backend.as_ref(),
+                        &self.snapshots,
+                        world,
+                        self.recovery.dimension,
+                        chunk,
+                    )
+                });
+                self.recovery.status = match &found {
+                    Ok(Some(recovered)) => format!(
+                        "Chunk {}, {}: newest good copy is in {} ({} newer snapshots skipped)",
+                        chunk.x, chunk.z, recovered.snapshot_id, recovered.skipped
+                    ),
+                    Ok(None) => format!(
+                        "No backup of this world has a good copy of chunk {}, {}",
+                        chunk.x, chunk.z
+                    ),
+                    Err(e) => format!("Search failed: {}", e),
+                };
+                self.recovery.found = found.ok().flatten();
+                Command::none()
+            }
+            Message::RecoveryRestorePressed => {
+                let (Some(world), Some(chunk), Some(recovered)) = (
+                    self.recovery.world.clone(),
+                    self.recovery.chunk(),
+                    self.recovery.found.take(),
+                ) else {
+                    return Command::none();
+                };
+                // Snapshot the world as it is first, so the splice can be undone
+                let result = self.open_backend().and_then(|backend| {
+                    file_operations::ensure_world_closed(&world)?;
+                    let safety_snapshot =
+                        file_operations::take_safety_snapshot(&world, None, backend.as_ref())?;
+                    region::restore_chunk(&world, self.recovery.dimension, chunk, &recovered)?;
+                    Ok(safety_snapshot)
+                });
+                self.recovery.status = match result {
+                    Ok(safety_snapshot) => format!(
+                        "Restored chunk {}, {} from {}. The world as it was is in {}",
+                        chunk.x, chunk.z, recovered.snapshot_id, safety_snapshot
+                    ),
+                    Err(e) => format!("Restore failed: {}", e),
+                };
+                self.refresh_snapshots();
+                Command::none()
+            }
+            Message::RestorePartsPressed(snapshot_id) => {
+                match self
+                    .open_backend()
+                    .and_then(|backend| selective::list_parts(backend.as_ref(), &snapshot_id))
+                {
+                    Ok(parts) => {
+                        self.partial_restore = Some(PartialRestore {
+                            snapshot_id,
+                            selected: vec![false; parts.len()],
+                            parts,
+                            ..PartialRestore::default()
+                        });
+                    }
+                    Err(e) => self.history_status = format!("Could not list parts: {}", e),
+                }
+                Command::none()
+            }
+            Message::RestorePartToggled(index, checked) => {
+                if let Some(partial) = &mut self.partial_restore {
+                    partial.selected[index] = checked;
+                }
+                Command::none()
+            }
+            Message::RestorePartPathChanged(path) => {
+                if let Some(partial) = &mut self.partial_restore {
+                    partial.custom_path = path;
+                }
+                Command::none()
+            }
+            Message::RestorePartPathAdded => {
+                let backend = self.open_backend();
+                let Some(partial) = &mut self.partial_restore else {
+                    return Command::none();
+                };
+                match backend.and_then(|backend| {
+                    selective::custom_part(
+                        backend.as_ref(),
+                        &partial.snapshot_id,
+                        &partial.custom_path,
+                    )
+                }) {
+                    Ok(part) => {
+                        partial.parts.push(part);
+                        partial.selected.push(true);
+                        partial.custom_path.clear();
+                        partial.status.clear();
+                    }
+                    Err(e) => partial.status = e.to_string(),
+                }
+                Command::none()
+            }
+            Message::RestoreSelectedPartsPressed => {
+                let initial_directory = self
+                    .config
+                    .minecraft_directory
+                    .clone()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let backend = self.open_backend();
+                let Some(partial) = &mut self.partial_restore else {
+                    return Command::none();
+                };
+                // Same target as a full restore: the folder the snapshot was taken from
+                let Some(target) = FileDialog::new()
+                    .set_title("Select the folder the backup was taken from")
+                    .set_directory(initial_directory)
+                    .pick_folder()
+                else {
+                    return Command::none();
+                };
+                let parts: Vec<selective::RestorePart> = partial
+                    .parts
+                    .iter()
+                    .zip(&partial.selected)
+                    .filter(|(_, selected)| **selected)
+                    .map(|(part, _)| part.clone())
+                    .collect();
+                partial.status = match backend.and_then(|backend| {
+                    if !confirm_restore_target(backend.as_ref(), &partial.snapshot_id, &target)? {
+                        return Ok(None);
+                    }
+                    selective::restore_parts(
+                        backend.as_ref(),
+                        &partial.snapshot_id,
+                        &parts,
+                        &target,
+                    )
+                    .map(Some)
+                }) {
+                    Ok(Some(safety_snapshot)) => format!(
+                        "Restored {} parts to {}. What they replaced is in {}",
+                        parts.len(),
+                        target.display(),
+                        safety_snapshot
+                    ),
+                    Ok(None) => String::new(),
+                    Err(e) => format!("Restore failed: {}", e),
+                };
+                self.refresh_snapshots();
+                Command::none()
+            }
+            Message::CloseRestorePartsPressed => {
+                self.partial_restore = None;
+                Command::none()
+            }
+            Message::PlayersPressed => {
+                self.show_players = !self.show_players;
+                self.inspector = PlayerInspector::default();
+                // Players are listed from the newest backup; a safety snapshot may hold only part of it
+                let Some(newest) = self
+                    .snapshots
+                    .iter()
+                    .find(|snapshot| !self.safety_snapshots.contains(*snapshot))
+                    .filter(|_| self.show_players)
+                else {
+                    return Command::none();
+                };
+                match self
+                    .open_backend()
+                    .and_then(|backend| players::list_players(backend.as_ref(), newest))
+                {
+                    Ok(players) if players.is_empty() => {
+                        self.inspector.status = format!("{} has no player files", newest);
+                    }
+                    Ok(players) => self.inspector.players = players,
+                    Err(e) => self.inspector.status = format!("Could not list players: {}", e),
+                }
+                Command::none()
+            }
+            Message::PlayerSelected(player) => {
+                match self.open_backend().and_then(|backend| {
+                    let mut unreadable = Vec::new();
+                    let history = players::player_history(
+                        backend.as_ref(),
+                        &self.snapshots,
+                        &player,
+                        &mut unreadable,
+                    )?;
+                    Ok((history, unreadable))
+                }) {
+                    Ok((history, unreadable)) => {
+                        self.inspector.status =
+                            format!("{} is in {} backups", player, history.len());
+                        if !unreadable.is_empty() {
+                            self.inspector.status.push_str(&format!(
+                                ". Their file could not be read in {}",
+                                unreadable.join("; ")
+                            ));
+                        }
+                        self.inspector.history = history;
+                    }
+                    Err(e) => {
+                        self.inspector.history.clear();
+                        self.inspector.status = format!("Could not read the player: {}", e);
+                    }
+                }
+                self.inspector.player = Some(player);
+                Command::none()
+            }
+            Message::PlayerFilterChanged(filter) => {
+                self.inspector.item_filter = filter;
+                Command::none()
+            }
+            Message::RestorePlayerPressed(snapshot_id) => {
+                let Some(player) = &self.inspector.player else {
+                    return Command::none();
+                };
+                let initial_directory = self
+                    .config
+                    .minecraft_directory
+                    .clone()
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| PathBuf::from("."));
+                let Some(target) = FileDialog::new()
+                    .set_title("Select the folder the backup was taken from")
+                    .set_directory(initial_directory)
+                    .pick_folder()
+                else {
+                    return Command::none();
+                };
+                // The game overwrites the file when an online player logs out
+                self.inspector.status = match self.open_backend().and_then(|backend| {
+                    if !confirm_restore_target(backend.as_ref(), &snapshot_id, &target)? {
+                        return Ok(None);
+                    }
+                    selective::restore_parts(
+                        backend.as_ref(),
+                        &snapshot_id,
+                        &[player.restore_part()],
+                        &target,
+                    )
+                    .map(Some)
+                }) {
+                    Ok(Some(safety_snapshot)) => format!(
+                        "Restored {} from {}. Make sure they are offline. The file it replaced is in {}",
+                        player, snapshot_id, safety_snapshot
+                    ),
+                    Ok(None) => String::new(),
+                    Err(e) => format!("Restore failed: {}", e),
+                };
+                self.refresh_snapshots();
+                Command::none()
+            }
+            Message::RunsPressed => {
+                self.runs_status.clear();
+                self.runs = match self.runs.take() {
+                    Some(_) => None,
+                    None => match runs::load() {
+                        Ok(mut runs) => {
+                            runs.reverse(); // Newest first
+                            Some(runs)
+                        }
+                        Err(e) => {
+                            self.history_status = format!("Could not read the run history: {}", e);
+                            None
+                        }
+                    },
+                };
+                Command::none()
+            }
+            Message::LogsPressed => {
+                // Only the end of the file, the rest is one click away with "Open Logs"
+                const SHOWN_LINES: usize = 200;
+                self.logs = match self.logs.take() {
+                    Some(_) => None,
+                    None => match logging::recent_lines(SHOWN_LINES) {
+                        Ok(lines) => Some(lines),
+                        Err(e) => Some(vec![format!("Could not read the log: {}", e)]),
+                    },
+                };
+                Command::none()
+            }
+            Message::OpenLogsPressed => {
+                if let Err(e) = open_in_file_manager(&logging::log_directory()) {
+                    error!("Could not open the log folder: {}", e);
+                }
+                Command::none()
+            }
+            Message::TestWebhooksPressed => {
+                self.runs_status = "Sending test messages...".to_string();
+                let targets = self.config.webhooks.clone();
+                Command::perform(
+                    blocking(move || {
+                        let failures: Vec<String> = targets
+                            .iter()
+                            .filter_map(|webhook| webhooks::send_test(webhook).err())
+                            .map(|e| e.to_string())
+                            .collect();
+                        if failures.is_empty() {
+                            format!("Sent a test message to {} webhooks", targets.len())
+                        } else {
+                            failures.join("\n")
+                        }
+                    }),
+                    Message::TestFinished,
+                )
+            }
+            Message::TestFinished(status) => {
+                self.runs_status = status;
+                Command::none()
+            }
+            Message::TestEmailPressed => {
+                self.runs_status = "Sending a test email...".to_string();
+                let settings = self.config.email.clone();
+                Command::perform(
+                    blocking(move || match alerts::send_test(&settings) {
+                        Ok(()) => format!("Sent a test email to {}", settings.smtp.to.join(", ")),
+                        Err(e) => e.to_string(),
+                    }),
+                    Message::TestFinished,
                 )
-                .unwrap();
-                println!("Selected Backup directory: {:?}", self.backup_directory);
+            }
+            Message::DismissFailurePressed => {
+                self.failure = None;
                 Command::none()
             }
 
             Message::ToggleTheme => {
                 self.toggle_theme();
                 // Persist the theme choice across restarts
-                if let Err(e) = config::save_configuration(
-                    &self.minecraft_directory,
-                    &self.backup_directory,
-                    self.schedule_hours,
-                    self.dark_theme,
-                ) {
-                    println!("Error saving configuration: {}", e);
-                }
+                self.save_config();
                 Command::none()
             }
 
@@ -404,7 +2343,7 @@ impl Application for RustCraft {
 
     fn view(&self) -> Element<'_, Self::Message> {
         let theme_toggle_button = Button::new(
-            asset_image(if self.dark_theme {
+            asset_image(if self.config.dark_theme {
                 "_sun.png"
             } else {
                 "_moon.png"
@@ -418,7 +2357,7 @@ impl Application for RustCraft {
 
         let theme_toggle = Tooltip::new(
             theme_toggle_button,
-            Text::new(if self.dark_theme {
+            Text::new(if self.config.dark_theme {
                 "Switch to light theme"
             } else {
                 "Switch to dark theme"
@@ -439,14 +2378,32 @@ impl Application for RustCraft {
             .padding(10)
             .style(button_styles::MinecraftButton);
 
-        // Enable start button only if both directories are selected and the schedule is not active
-        if self.minecraft_directory.is_some() && self.backup_directory.is_some()
-            || self.active_schedule
-        {
+        // Enable start button only if a source and destination are selected and the schedule is not active
+        if self.has_source() && self.has_destination() || self.active_schedule {
             start_button = start_button.on_press(Message::StartPressed);
         }
 
-        let control_buttons = Row::new().spacing(10).push(start_button);
+        let mut history_button = Button::new(Text::new("History").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+
+        if self.has_destination() {
+            history_button = history_button.on_press(Message::HistoryPressed);
+        }
+
+        let mut discover_button = Button::new(Text::new("Find Saves").font(MONOCRAFT))
+            .padding(10)
+            .style(button_styles::MinecraftButton);
+
+        if !self.active_schedule && self.config.source != SourceKind::Sftp {
+            discover_button = discover_button.on_press(Message::DiscoverPressed);
+        }
+
+        let control_buttons = Row::new()
+            .spacing(10)
+            .push(start_button)
+            .push(history_button)
+            .push(discover_button);
 
         let mut minecraft_dir_button = Button::new(
             Text::new("Select Minecraft Directory")
@@ -457,12 +2414,23 @@ impl Application for RustCraft {
         .width(Length::Fixed(370f32))
         .style(button_styles::MinecraftButton);
 
-        if !self.active_schedule {
+        // Remote sources are set up in config.json, the folder picker only applies to worlds on this machine
+        if !self.active_schedule && self.config.source != SourceKind::Sftp {
             minecraft_dir_button = minecraft_dir_button.on_press(Message::MinecraftDirPressed);
         }
 
+        let backup_source = match self.config.source {
+            SourceKind::Local | SourceKind::Server => self.config.minecraft_directory.clone(),
+            SourceKind::Sftp => Some(format!(
+                "sftp://{}@{}{}",
+                self.config.sftp_source.username,
+                self.config.sftp_source.host,
+                self.config.sftp_source.remote_path
+            )),
+        };
+
         // Show a truncated path to keep the layout tidy; the full path is in a tooltip
-        let minecraft_dir_text: Element<Message> = match &self.minecraft_directory {
+        let minecraft_dir_text: Element<Message> = match &backup_source {
             Some(path) => Tooltip::new(
                 Text::new(truncate_path(path))
                     .font(MONOCRAFT)
@@ -489,11 +2457,17 @@ impl Application for RustCraft {
         .width(Length::Fixed(370f32))
         .style(button_styles::MinecraftButton);
 
-        if !self.active_schedule {
+        // Remote destinations are set up in config.json, the folder picker only applies to local backups
+        if !self.active_schedule && self.config.destination == DestinationKind::Local {
             backup_dir_button = backup_dir_button.on_press(Message::BackupDirPressed);
         }
 
-        let backup_dir_text: Element<Message> = match &self.backup_directory {
+        // Opening the backend here would unlock encrypted destinations on every redraw
+        let backup_destination = match self.config.destination {
+            DestinationKind::Local => self.config.backup_directory.clone(),
+            _ => storage::backend::describe_destination(&self.config),
+        };
+        let backup_dir_text: Element<Message> = match &backup_destination {
             Some(path) => Tooltip::new(
                 Text::new(truncate_path(path))
                     .font(MONOCRAFT)
@@ -511,19 +2485,26 @@ impl Application for RustCraft {
                 .into(),
         };
 
-        let schedule_slider = Slider::new(0..=24, self.schedule_hours, Message::ScheduleChanged)
-            .step(1)
-            .width(Length::Fixed(200f32))
-            .style(slider_styles::MinecraftSlider);
+        let schedule_slider = Slider::new(
+            0..=24,
+            self.config.backup_frequency,
+            Message::ScheduleChanged,
+        )
+        .step(1)
+        .width(Length::Fixed(200f32))
+        .style(slider_styles::MinecraftSlider);
 
-        let schedule_text = if self.schedule_hours == 0 {
+        let schedule_text = if self.config.backup_frequency == 0 {
             Text::new("Perform a one-time backup")
                 .font(MONOCRAFT)
                 .size(text_sizes::SECONDARY)
         } else {
-            Text::new(format!("Schedule every {} hours", self.schedule_hours))
-                .font(MONOCRAFT)
-                .size(text_sizes::SECONDARY)
+            Text::new(format!(
+                "Schedule every {} hours",
+                self.config.backup_frequency
+            ))
+            .font(MONOCRAFT)
+            .size(text_sizes::SECONDARY)
         };
 
         let minecraft_dir_column = Column::new()
@@ -533,13 +2514,17 @@ impl Application for RustCraft {
             .push(minecraft_dir_button)
             .push(minecraft_dir_text);
 
-        let backup_dir_column = Column::new()
+        let mut backup_dir_column = Column::new()
             .padding(10)
             .spacing(10)
             .align_items(Alignment::Center)
             .push(backup_dir_button)
             .push(backup_dir_text);
 
+        if self.config.encryption.enabled {
+            backup_dir_column = backup_dir_column.push(encryption_warning());
+        }
+
         let schedule_slider_column = Column::new()
             .padding(10)
             .spacing(10)
@@ -551,7 +2536,7 @@ impl Application for RustCraft {
         let timer_display: Element<Message> = if self.active_schedule {
             if let Some(last_backup_time) = self.last_backup_time {
                 let elapsed = last_backup_time.elapsed().as_secs();
-                let next_backup_in = (self.schedule_hours * 3600) as u64 - elapsed;
+                let next_backup_in = (self.config.backup_frequency * 3600) as u64 - elapsed;
                 let hours = next_backup_in / 3600;
                 let minutes = (next_backup_in % 3600) / 60;
                 let seconds = next_backup_in % 60;
@@ -573,22 +2558,46 @@ impl Application for RustCraft {
             Text::new("").into()
         };
 
-        let image = asset_image(&self.image_path).width(Length::Fill);
+        let image = match self.preview.as_ref().filter(|_| self.show_history) {
+            Some(preview) => Image::new(preview.clone()).width(Length::Fill),
+            None => asset_image(&self.image_path).width(Length::Fill),
+        };
 
         let image_column = Column::new()
             .align_items(Alignment::Center)
             .width(Length::FillPortion(1))
             .push(image);
 
-        let buttons_column = Column::new()
-            .align_items(Alignment::Center)
-            .spacing(20)
-            .padding(20)
-            .push(minecraft_dir_column)
-            .push(backup_dir_column)
-            .push(schedule_slider_column)
-            .push(control_buttons)
-            .push(timer_display);
+        let buttons_column: Element<Message> = if let Some((old_id, new_id, diff)) =
+            self.snapshot_diff.as_ref().filter(|_| self.show_history)
+        {
+            self.diff_view(old_id, new_id, diff)
+        } else if let Some(partial) = self.partial_restore.as_ref().filter(|_| self.show_history) {
+            self.parts_view(partial)
+        } else if let Some(lines) = self.logs.as_ref().filter(|_| self.show_history) {
+            self.logs_view(lines)
+        } else if let Some(runs) = self.runs.as_ref().filter(|_| self.show_history) {
+            self.runs_view(runs)
+        } else if self.show_history && self.show_players {
+            self.players_view()
+        } else if self.show_history && self.show_recovery {
+            self.recovery_view()
+        } else if self.show_history {
+            self.history_view()
+        } else if self.show_discovery {
+            self.discovery_view()
+        } else {
+            Column::new()
+                .align_items(Alignment::Center)
+                .spacing(20)
+                .padding(20)
+                .push(minecraft_dir_column)
+                .push(backup_dir_column)
+                .push(schedule_slider_column)
+                .push(control_buttons)
+                .push(timer_display)
+                .into()
+        };
 
         // Float the theme toggle in the top-right corner of the right column,
         // so the main content keeps the full window height.
@@ -601,6 +2610,7 @@ impl Application for RustCraft {
                     .align_x(Horizontal::Right)
                     .padding([8, 8, 0, 0]),
             )
+            .push_maybe(self.failure.as_ref().map(failure_banner))
             .push(
                 Container::new(buttons_column)
                     .width(Length::Fill)
@@ -625,7 +2635,7 @@ impl Application for RustCraft {
     }
 
     fn theme(&self) -> Theme {
-        if self.dark_theme {
+        if self.config.dark_theme {
             Theme::custom(
                 "Dark Theme".to_string(),
                 theme::Palette {
@@ -650,11 +2660,8 @@ impl Application for RustCraft {
         }
     }
 
+    // Ticks run even without a schedule, to pick up retries clicked on notifications
     fn subscription(&self) -> Subscription<Self::Message> {
-        if self.active_schedule {
-            every(Duration::from_secs(1)).map(Message::Tick)
-        } else {
-            Subscription::none()
-        }
+        every(Duration::from_secs(1)).map(Message::Tick)
     }
 }
@@ -0,0 +1,257 @@
+use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+use std::{collections::HashMap, io};
+
+use crate::file_operations::{read_manifest, Manifest, RESERVED_FOLDER};
+use crate::nbt::Tag;
+use crate::region;
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// Name of the map preview kept in a snapshot's reserved folder. It is not listed in the
+/// manifest, so snapshots without one are still complete.
+const PREVIEW_FILE: &str = "preview.png";
+
+/// Longest side of a preview in pixels. Larger worlds are scaled down.
+const MAX_SIZE: i64 = 512;
+
+/// Total size and `(path, region coordinates)` of a world's overworld region files.
+type WorldRegions<'a> = (u64, Vec<(&'a str, (i32, i32))>);
+
+/// Key of a snapshot's map preview, where no file from the world can be.
+pub fn preview_key(snapshot_id: &str) -> String {
+    join_key(&[snapshot_id, RESERVED_FOLDER, PREVIEW_FILE])
+}
+
+/// Fetch the map preview of a snapshot, rendering and storing it first if it has none.
+/// Returns `None` for snapshots without any overworld region files.
+pub fn load_or_render(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+) -> io::Result<Option<Vec<u8>>> {
+    let key = preview_key(snapshot_id);
+    if backend.stat(&key)?.is_some() {
+        let mut png = Vec::new();
+        backend.get(&key, &mut png)?;
+        return Ok(Some(png));
+    }
+
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let png = render(&manifest, |path| {
+        let mut data = Vec::new();
+        backend.get(&join_key(&[snapshot_id, path]), &mut data)?;
+        Ok(data)
+    })?;
+    if let Some(png) = &png {
+        backend.put(&key, &mut png.as_slice())?;
+    }
+    Ok(png)
+}
+
+/// Render a top-down PNG of the largest world in a snapshot, reading region files through
+/// `read`, which takes paths as listed in the manifest. This only needs the CPU, so it
+/// works from the live source during a backup as well as from any destination.
+pub fn render(
+    manifest: &Manifest,
+    read: impl Fn(&str) -> io::Result<Vec<u8>>,
+) -> io::Result<Option<Vec<u8>>> {
+    // Overworld region files by world, with their total size
+    let mut worlds: HashMap<&str, WorldRegions> = HashMap::new();
+    for entry in &manifest.files {
+        let Some((folder, file)) = entry.path.rsplit_once('/') else {
+            continue;
+        };
+        let Some(world) = folder
+            .strip_suffix("region")
+            .filter(|world| world.is_empty() || world.ends_with('/'))
+        else {
+            continue;
+        };
+        // Nether and End regions sit in DIM-1/region and DIM1/region
+        if world.ends_with("DIM-1/") || world.ends_with("DIM1/") || world.contains("dimensions/") {
+            continue;
+        }
+        if let Some(coordinates) = region::region_coordinates(file) {
+            let (size, regions) = worlds.entry(world).or_default();
+            *size += entry.size;
+            regions.push((&entry.path, coordinates));
+        }
+    }
+    let Some((_, regions)) = worlds.into_values().max_by_key(|(size, _)| *size) else {
+        return Ok(None);
+    };
+
+    // Region files bound the map; each pixel covers `scale` blocks in both directions
+    let min_x = regions.iter().map(|(_, (x, _))| *x).min().unwrap_or(0) as i64 * 512;
+    let min_z = regions.iter().map(|(_, (_, z))| *z).min().unwrap_or(0) as i64 * 512;
+    let max_x = (regions.iter().map(|(_, (x, _))| *x).max().unwrap_or(0) as i64 + 1) * 512;
+    let max_z = (regions.iter().map(|(_, (_, z))| *z).max().unwrap_or(0) as i64 + 1) * 512;
+    let scale = ((max_x - min_x).max(max_z - min_z) + MAX_SIZE - 1) / MAX_SIZE;
+    // Rounded up: the last blocks of the shorter side still need a pixel when the regions
+    // do not form a square
+    let width = ((max_x - min_x + scale - 1) / scale) as u32;
+    let height = ((max_z - min_z + scale - 1) / scale) as u32;
+    let mut image = RgbImage::from_pixel(width, height, Rgb([24, 26, 30]));
+
+    for (path, coordinates) in regions {
+        let data = read(path)?;
+        region::for_each_chunk(coordinates, &data, |chunk, tag| {
+            let Some(columns) = top_blocks(tag) else {
+                return;
+            };
+            for (index, column) in columns.iter().enumerate() {
+                let Some((name, y)) = column else {
+                    continue;
+                };
+                let (local_x, local_z) = (index % 16, index / 16);
+                let block_x = chunk.x as i64 * 16 + local_x as i64;
+                let block_z = chunk.z as i64 * 16 + local_z as i64;
+                // Light slopes facing north and darken the ones facing away, for relief
+                let north = local_z
+                    .checked_sub(1)
+                    .and_then(|z| columns[z * 16 + local_x].as_ref())
+                    .map(|(_, north_y)| *north_y);
+                let shade = match north {
+                    Some(north_y) if *y > north_y => 1.15,
+                    Some(north_y) if *y < north_y => 0.85,
+                    _ => 1.0,
+                };
+                let color = block_color(name).map(|c| (c as f32 * shade).min(255.0) as u8);
+                let (x, z) = (
+                    ((block_x - min_x) / scale) as u32,
+                    ((block_z - min_z) / scale) as u32,
+                );
+                // `put_pixel` panics outside the image, and a preview is never worth that
+                if x < width && z < height {
+                    image.put_pixel(x, z, Rgb(color));
+                }
+            }
+        });
+    }
+
+    let mut png = Vec::new();
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(Some(png))
+}
+
+/// Highest block of each of the 256 columns of a chunk (index `z * 16 + x`), with its height.
+fn top_blocks(chunk: &Tag) -> Option<Vec<Option<(&str, i32)>>> {
+    // Before 1.18 everything sits under `Level`, with capitalised section fields
+    let root = chunk.get("Level").unwrap_or(chunk);
+    let sections = root
+        .get("sections")
+        .or_else(|| root.get("Sections"))?
+        .as_list()?;
+    let min_y = root
+        .get("yPos")
+        .and_then(|y| y.as_i64())
+        .map_or(0, |y| y as i32 * 16);
+
+    // Section height to (palette, packed block indices)
+    let mut by_height: HashMap<i32, (Vec<&str>, &[i64])> = HashMap::new();
+    for section in sections {
+        let Some(y) = section.get("Y").and_then(|y| y.as_i64()) else {
+            continue;
+        };
+        let states = section.get("block_states").unwrap_or(section);
+        let palette = states.get("palette").or_else(|| states.get("Palette"));
+        let Some(palette) = palette.and_then(|palette| palette.as_list()) else {
+            continue;
+        };
+        let names = palette
+            .iter()
+            .map(|state| {
+                state
+                    .get("Name")
+                    .and_then(|name| name.as_str())
+                    .unwrap_or("air")
+            })
+            .collect();
+        let data = states
+            .get("data")
+            .or_else(|| states.get("BlockStates"))
+            .and_then(|data| data.as_long_array())
+            .unwrap_or_default();
+        by_height.insert(y as i32, (names, data));
+    }
+
+    let block_at = |x: usize, y: i32, z: usize| -> Option<&str> {
+        let (palette, data) = by_height.get(&y.div_euclid(16))?;
+        if palette.len() == 1 {
+            return palette.first().copied();
+        }
+        let index = y.rem_euclid(16) as usize * 256 + z * 16 + x;
+        let bits = (usize::BITS - (palette.len() - 1).leading_zeros()).max(4) as usize;
+        palette.get(packed_value(data, index, bits)?).copied()
+    };
+
+    let surface = root
+        .get("Heightmaps")
+        .and_then(|heightmaps| heightmaps.get("WORLD_SURFACE"))
+        .and_then(|heightmap| heightmap.as_long_array());
+    let top_section = by_height.keys().max().copied()?;
+
+    let columns = (0..256)
+        .map(|index| {
+            let (x, z) = (index % 16, index / 16);
+            if let Some(surface) = surface {
+                // Heights count from the bottom of the world, 0 meaning an empty column
+                let height = packed_value(surface, index, 9)? as i32;
+                let y = min_y + height - 1;
+                return block_at(x, y, z).map(|name| (name, y));
+            }
+            // Without a heightmap, walk down from the top until something is not air
+            (min_y..(top_section + 1) * 16).rev().find_map(|y| {
+                block_at(x, y, z)
+                    .filter(|name| !is_air(name))
+                    .map(|name| (name, y))
+            })
+        })
+        .collect();
+    Some(columns)
+}
+
+/// Since 1.16, values are packed into longs without spanning two of them.
+fn packed_value(data: &[i64], index: usize, bits: usize) -> Option<usize> {
+    let per_long = 64 / bits;
+    let long = *data.get(index / per_long)? as u64;
+    let shift = (index % per_long) * bits;
+    Some(((long >> shift) & ((1 << bits) - 1)) as usize)
+}
+
+fn is_air(name: &str) -> bool {
+    name.ends_with("air")
+}
+
+/// Rough map colour of a block, by name.
+fn block_color(name: &str) -> [u8; 3] {
+    let name = name.strip_prefix("minecraft:").unwrap_or(name);
+    let colors: [(&str, [u8; 3]); 22] = [
+        ("water", [52, 92, 196]),
+        ("lava", [207, 92, 20]),
+        ("ice", [160, 188, 255]),
+        ("snow", [242, 242, 242]),
+        ("grass_block", [102, 153, 51]),
+        ("leaves", [52, 110, 34]),
+        ("sand", [219, 207, 163]),
+        ("gravel", [136, 126, 126]),
+        ("clay", [160, 166, 179]),
+        ("dirt", [134, 96, 67]),
+        ("mud", [60, 57, 61]),
+        ("podzol", [91, 63, 24]),
+        ("log", [102, 81, 51]),
+        ("planks", [162, 130, 78]),
+        ("deepslate", [80, 80, 82]),
+        ("stone", [125, 125, 125]),
+        ("terracotta", [152, 94, 67]),
+        ("netherrack", [111, 54, 52]),
+        ("soul", [81, 62, 50]),
+        ("end_stone", [219, 222, 158]),
+        ("obsidian", [20, 18, 29]),
+        ("grass", [110, 160, 60]),
+    ];
+    colors
+        .iter()
+        .find(|(pattern, _)| name.contains(pattern))
+        .map_or([112, 112, 112], |(_, color)| *color)
+}
@@ -0,0 +1,286 @@
+use flate2::read::GzDecoder;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// Byte order of an NBT document. Java Edition writes big-endian files,
+/// Bedrock Edition little-endian ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Big,
+    Little,
+}
+
+/// A single NBT value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(BTreeMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    /// Look up a field of a compound tag.
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(fields) => fields.get(key),
+            _ => None,
+        }
+    }
+
+    /// Any integer tag, widened to `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(value) => Some(value.into()),
+            Tag::Short(value) => Some(value.into()),
+            Tag::Int(value) => Some(value.into()),
+            Tag::Long(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Any floating point tag, widened to `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Tag::Float(value) => Some(value.into()),
+            Tag::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Values are shown the way `/data get` prints them, without type suffixes.
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+            f.write_str("[")?;
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", item)?;
+            }
+            f.write_str("]")
+        }
+        match self {
+            Tag::Byte(value) => write!(f, "{}", value),
+            Tag::Short(value) => write!(f, "{}", value),
+            Tag::Int(value) => write!(f, "{}", value),
+            Tag::Long(value) => write!(f, "{}", value),
+            Tag::Float(value) => write!(f, "{}", value),
+            Tag::Double(value) => write!(f, "{}", value),
+            Tag::String(value) => write!(f, "{:?}", value),
+            Tag::ByteArray(values) => list(f, values),
+            Tag::IntArray(values) => list(f, values),
+            Tag::LongArray(values) => list(f, values),
+            Tag::List(items) => list(f, items),
+            Tag::Compound(fields) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// Parse an uncompressed NBT document, returning the root tag's name and value.
+pub fn parse(data: &[u8], endian: Endian) -> io::Result<(String, Tag)> {
+    let mut reader = Reader {
+        data,
+        position: 0,
+        endian,
+    };
+    let kind = reader.u8()?;
+    if kind != 10 {
+        return Err(invalid("NBT root is not a compound tag"));
+    }
+    let name = reader.string()?;
+    let root = reader.payload(kind, 0)?;
+    Ok((name, root))
+}
+
+/// Read a gzip-compressed Java Edition file such as `level.dat` or a player's `.dat` file.
+pub fn read_gzip_file(path: &Path) -> io::Result<Tag> {
+    parse_gzip(&fs::read(path)?)
+}
+
+/// Parse the contents of a gzip-compressed Java Edition file.
+pub fn parse_gzip(compressed: &[u8]) -> io::Result<Tag> {
+    let mut data = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut data)?;
+    Ok(parse(&data, Endian::Big)?.1)
+}
+
+/// Nesting limit, so a damaged file cannot overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    endian: Endian,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + N)
+            .ok_or_else(|| invalid("NBT data ends unexpectedly"))?;
+        self.position += N;
+        Ok(bytes.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn i16(&mut self) -> io::Result<i16> {
+        let bytes = self.take()?;
+        Ok(match self.endian {
+            Endian::Big => i16::from_be_bytes(bytes),
+            Endian::Little => i16::from_le_bytes(bytes),
+        })
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let bytes = self.take()?;
+        Ok(match self.endian {
+            Endian::Big => i32::from_be_bytes(bytes),
+            Endian::Little => i32::from_le_bytes(bytes),
+        })
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        let bytes = self.take()?;
+        Ok(match self.endian {
+            Endian::Big => i64::from_be_bytes(bytes),
+            Endian::Little => i64::from_le_bytes(bytes),
+        })
+    }
+
+    fn length(&mut self) -> io::Result<usize> {
+        let length = self.i32()?;
+        // Every element takes at least one byte, so longer lengths can only come from damage
+        if length < 0 || length as usize > self.data.len() - self.position {
+            return Err(invalid("Invalid NBT length"));
+        }
+        Ok(length as usize)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let length = self.i16()? as u16 as usize;
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| invalid("NBT data ends unexpectedly"))?;
+        self.position += length;
+        // Java writes modified UTF-8, which only differs for characters outside the BMP
+        Ok(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    fn payload(&mut self, kind: u8, depth: usize) -> io::Result<Tag> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("NBT data is nested too deeply"));
+        }
+        Ok(match kind {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let length = self.length()?;
+                let mut bytes = Vec::with_capacity(length);
+                for _ in 0..length {
+                    bytes.push(self.u8()? as i8);
+                }
+                Tag::ByteArray(bytes)
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let item_kind = self.u8()?;
+                let length = self.length()?;
+                let mut items = Vec::with_capacity(length);
+                // Empty lists are written with an item type of 0 (End)
+                if item_kind != 0 {
+                    for _ in 0..length {
+                        items.push(self.payload(item_kind, depth + 1)?);
+                    }
+                }
+                Tag::List(items)
+            }
+            10 => {
+                let mut fields = BTreeMap::new();
+                loop {
+                    let field_kind = self.u8()?;
+                    if field_kind == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    fields.insert(name, self.payload(field_kind, depth + 1)?);
+                }
+                Tag::Compound(fields)
+            }
+            11 => {
+                let length = self.length()?;
+                let mut values = Vec::with_capacity(length);
+                for _ in 0..length {
+                    values.push(self.i32()?);
+                }
+                Tag::IntArray(values)
+            }
+            12 => {
+                let length = self.length()?;
+                let mut values = Vec::with_capacity(length);
+                for _ in 0..length {
+                    values.push(self.i64()?);
+                }
+                Tag::LongArray(values)
+            }
+            other => return Err(invalid(&format!("Unknown NBT tag type {}", other))),
+        })
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
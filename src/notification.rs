@@ -1,25 +1,130 @@
+use chrono::{DateTime, Local};
 use notify_rust::Notification;
+use tracing::warn;
 
-pub fn trigger_notification(success: bool, error_message: Option<&str>) {
-    if success {
-        if let Err(e) = Notification::new()
-            .appname("RustCraft")
-            .summary("RustCraft - Backup Completed") //remove
-            .body("Your Minecraft worlds have been successfully saved.")
-            .icon("./assets/icon.ico")
-            .show()
-        {
-            eprintln!("Failed to show notification: {:?}", e);
+use crate::config::NotificationSettings;
+use crate::runs::{self, RunRecord};
+
+/// Buttons offered on notifications, where the desktop supports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    OpenBackupFolder,
+    Retry,
+    ViewLog,
+}
+
+impl Action {
+    fn id(self) -> &'static str {
+        match self {
+            Action::OpenBackupFolder => "open-backup-folder",
+            Action::Retry => "retry",
+            Action::ViewLog => "view-log",
         }
-    } else if let Some(msg) = error_message {
-        if let Err(e) = Notification::new()
-            .appname("RustCraft")
-            .summary("RustCraft - Backup Error") //remove
-            .body(msg)
-            .icon("./assets/error.png")
-            .show()
-        {
-            eprintln!("Failed to show notification: {:?}", e);
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Action::OpenBackupFolder => "Open backup folder",
+            Action::Retry => "Retry",
+            Action::ViewLog => "View log",
         }
     }
 }
+
+/// Tell the user a backup finished: which worlds, how big, how long it took and when the next one runs.
+pub fn notify_success(
+    settings: &NotificationSettings,
+    run: &RunRecord,
+    next: Option<DateTime<Local>>,
+    actions: &[Action],
+    on_action: impl FnOnce(Action) + Send + 'static,
+) {
+    if settings.mute_success {
+        return;
+    }
+    let worlds = if run.worlds.is_empty() {
+        "Your Minecraft worlds".to_string()
+    } else {
+        run.worlds.join(", ")
+    };
+    let mut body = format!(
+        "{} saved: {} in {}s.",
+        worlds,
+        runs::format_size(run.bytes),
+        (run.finished - run.started).num_seconds()
+    );
+    if let Some(next) = next {
+        body.push_str(&format!(" Next backup at {}.", next.format("%H:%M")));
+    }
+    if !run.warnings.is_empty() {
+        body.push_str(&format!(" {} warnings, see the log.", run.warnings.len()));
+    }
+    show(
+        Notification::new()
+            .summary("RustCraft - Backup Completed")
+            .body(&body)
+            .icon("./assets/icon.ico"),
+        actions,
+        on_action,
+    );
+}
+
+/// Tell the user a backup failed and why.
+pub fn notify_failure(
+    settings: &NotificationSettings,
+    error_message: &str,
+    actions: &[Action],
+    on_action: impl FnOnce(Action) + Send + 'static,
+) {
+    if settings.mute_failure {
+        return;
+    }
+    show(
+        Notification::new()
+            .summary("RustCraft - Backup Error")
+            .body(error_message)
+            .icon("./assets/error.png"),
+        actions,
+        on_action,
+    );
+}
+
+// Notification servers on Linux report which button was clicked; waiting for it blocks,
+// so it happens on a thread of its own.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show(
+    notification: &mut Notification,
+    actions: &[Action],
+    on_action: impl FnOnce(Action) + Send + 'static,
+) {
+    notification.appname("RustCraft");
+    for action in actions {
+        notification.action(action.id(), action.label());
+    }
+    match notification.show() {
+        Ok(handle) if !actions.is_empty() => {
+            let actions = actions.to_vec();
+            std::thread::spawn(move || {
+                handle.wait_for_action(|id| {
+                    if let Some(action) = actions.into_iter().find(|action| action.id() == id) {
+                        on_action(action);
+                    }
+                });
+            });
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Failed to show notification: {}", e),
+    }
+}
+
+// Windows and macOS notifications cannot report clicks on buttons, so none are shown.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+fn show(
+    notification: &mut Notification,
+    _actions: &[Action],
+    _on_action: impl FnOnce(Action) + Send + 'static,
+) {
+    if let Err(e) = notification.appname("RustCraft").show() {
+        warn!("Failed to show notification: {}", e);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, Seek, Write},
+    path::{Path, PathBuf},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::bedrock;
+use crate::file_operations::read_manifest;
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// Whether a file is left out of exported worlds. `relative` is relative to the world
+/// folder and uses `/` as the separator.
+///
+/// `session.lock` would stop the world from opening while another copy is running,
+/// the world's logs are of no use to whoever gets it, and LevelDB recreates its lock file.
+/// A `logs` folder deeper in the world may belong to a datapack or mod, so it stays.
+fn is_excluded(relative: &str) -> bool {
+    let segments: Vec<&str> = relative.split('/').collect();
+    segments.last() == Some(&"session.lock")
+        || segments.first() == Some(&"logs")
+        || segments.ends_with(&[bedrock::LEVELDB_FOLDER, bedrock::LEVELDB_LOCK])
+}
+
+fn zip_options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated)
+}
+
+/// Pack a world folder into a zip, or a `.mcworld` for Bedrock (same format, other extension).
+/// The world's files sit at the root of the archive.
+pub fn export_world(world: &Path, destination: &Path) -> io::Result<()> {
+    let db = world.join(bedrock::LEVELDB_FOLDER);
+    let _lock = if bedrock::is_leveldb(&db) {
+        Some(bedrock::lock_leveldb(&db)?)
+    } else {
+        None
+    };
+
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    add_directory(&mut zip, world, world)?;
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+fn add_directory<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    base: &Path,
+    dir: &Path,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .strip_prefix(base)
+            .unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+        if is_excluded(&name) {
+            continue;
+        }
+        if path.is_dir() {
+            zip.add_directory(name, zip_options())
+                .map_err(io::Error::other)?;
+            add_directory(zip, base, &path)?;
+        } else {
+            zip.start_file(name, zip_options())
+                .map_err(io::Error::other)?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// Worlds in a snapshot, as folders relative to the snapshot root. A world is any
+/// folder with a `level.dat`; an empty string means the snapshot is a single world.
+pub fn snapshot_worlds(backend: &dyn StorageBackend, snapshot_id: &str) -> io::Result<Vec<String>> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let worlds: BTreeSet<String> = manifest
+        .files
+        .iter()
+        .filter_map(|entry| match entry.path.rsplit_once('/') {
+            Some((world, "level.dat")) => Some(world.to_string()),
+            None if entry.path == "level.dat" => Some(String::new()),
+            _ => None,
+        })
+        .collect();
+    Ok(worlds.into_iter().collect())
+}
+
+/// Whether `world` in a snapshot is a Bedrock world, so it is exported as `.mcworld`.
+pub fn snapshot_world_is_bedrock(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    world: &str,
+) -> io::Result<bool> {
+    let current = join_key(&[world, bedrock::LEVELDB_FOLDER, "CURRENT"]);
+    Ok(read_manifest(backend, snapshot_id)?
+        .files
+        .iter()
+        .any(|entry| entry.path == current))
+}
+
+/// Pack one world of a snapshot into a zip, streaming the files straight from the destination.
+pub fn export_snapshot_world(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    world: &str,
+    destination: &Path,
+) -> io::Result<()> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let mut zip = ZipWriter::new(File::create(destination)?);
+    let mut exported = 0;
+
+    for entry in &manifest.files {
+        let relative = if world.is_empty() {
+            entry.path.as_str()
+        } else {
+            match entry
+                .path
+                .strip_prefix(world)
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(relative) => relative,
+                None => continue,
+            }
+        };
+        if is_excluded(relative) {
+            continue;
+        }
+        zip.start_file(relative, zip_options())
+            .map_err(io::Error::other)?;
+        backend.get(&join_key(&[snapshot_id, &entry.path]), &mut zip)?;
+        exported += 1;
+    }
+    if exported == 0 {
+        return Err(io::Error::other(format!(
+            "Snapshot {} has no world \"{}\"",
+            snapshot_id, world
+        )));
+    }
+    zip.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// Unpack a world zip or `.mcworld` into a new folder of `saves`, returning that folder.
+/// Existing worlds are never overwritten: a name that is taken gets a number appended.
+pub fn import_world(file: &Path, saves: &Path) -> io::Result<PathBuf> {
+    let mut archive = ZipArchive::new(File::open(file)?).map_err(io::Error::other)?;
+
+    // Worlds are often zipped together with their folder; the world root is wherever level.dat is
+    let root = (0..archive.len())
+        .filter_map(|index| archive.by_index(index).ok()?.enclosed_name())
+        .filter(|path| path.file_name().is_some_and(|name| name == "level.dat"))
+        .min_by_key(|path| path.components().count())
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .ok_or_else(|| io::Error::other(format!("{} contains no level.dat", file.display())))?;
+
+    let name = root
+        .file_name()
+        .or_else(|| file.file_stem())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let target = unique_folder(saves, &name);
+    fs::create_dir_all(&target)?;
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index).map_err(io::Error::other)?;
+        // `enclosed_name` rejects entries that would escape the target folder
+        let Some(relative) = entry
+            .enclosed_name()
+            .and_then(|path| path.strip_prefix(&root).ok().map(Path::to_path_buf))
+        else {
+            continue;
+        };
+        let output = target.join(relative);
+        if entry.is_dir() {
+            fs::create_dir_all(&output)?;
+        } else {
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            io::copy(&mut entry, &mut File::create(&output)?)?;
+        }
+    }
+
+    // Bedrock will not open a world without its database folder, even an empty one
+    if file
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mcworld"))
+    {
+        fs::create_dir_all(target.join(bedrock::LEVELDB_FOLDER))?;
+    }
+    Ok(target)
+}
+
+/// A folder in `parent` that does not exist yet, named after `name`.
+fn unique_folder(parent: &Path, name: &str) -> PathBuf {
+    let name: String = name
+        .chars()
+        .map(|c| if r#"<>:"/\|?*"#.contains(c) { '_' } else { c })
+        .collect();
+    let name = match name.trim() {
+        "" => "world",
+        name => name,
+    };
+    let mut candidate = parent.join(name);
+    let mut counter = 2;
+    while candidate.exists() {
+        candidate = parent.join(format!("{} ({})", name, counter));
+        counter += 1;
+    }
+    candidate
+}
@@ -0,0 +1,202 @@
+use std::{fmt, io};
+use tracing::warn;
+
+use crate::file_operations::read_manifest;
+use crate::nbt::{self, Tag};
+use crate::selective::{self, RestorePart};
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// A player's save file in one world, `<world>/playerdata/<uuid>.dat`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerFile {
+    /// World folder relative to the snapshot root, empty when the snapshot is the world.
+    pub world: String,
+    pub uuid: String,
+    /// Name from `usercache.json`, when the snapshot has one that knows the player.
+    pub name: Option<String>,
+}
+
+impl PlayerFile {
+    pub fn path(&self) -> String {
+        join_key(&[&self.world, "playerdata", &format!("{}.dat", self.uuid)])
+    }
+
+    /// The player file as a part for a selective restore.
+    pub fn restore_part(&self) -> RestorePart {
+        RestorePart {
+            label: self.to_string(),
+            world: self.world.clone(),
+            paths: vec![self.path()],
+        }
+    }
+}
+
+impl fmt::Display for PlayerFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name.as_deref().unwrap_or(&self.uuid))?;
+        if !self.world.is_empty() {
+            write!(f, " ({})", self.world)?;
+        }
+        Ok(())
+    }
+}
+
+/// What a player had and where they were when a snapshot was taken.
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub snapshot_id: String,
+    /// Items like `12 diamond`, armour and off-hand included.
+    pub inventory: Vec<String>,
+    pub ender_items: Vec<String>,
+    pub position: Option<[f64; 3]>,
+    pub dimension: String,
+    pub xp_level: i64,
+}
+
+impl PlayerState {
+    pub fn summary(&self) -> String {
+        let position = match self.position {
+            Some([x, y, z]) => format!("{:.0}, {:.0}, {:.0}", x, y, z),
+            None => "unknown position".to_string(),
+        };
+        format!(
+            "{} at {}, level {}",
+            self.dimension, position, self.xp_level
+        )
+    }
+
+    /// Whether any item, in the inventory or the ender chest, contains `filter`.
+    pub fn has_item(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        self.inventory
+            .iter()
+            .chain(&self.ender_items)
+            .any(|item| item.to_lowercase().contains(&filter))
+    }
+}
+
+/// Players with a save file in a snapshot, named through its `usercache.json`.
+pub fn list_players(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+) -> io::Result<Vec<PlayerFile>> {
+    let names = selective::player_names(backend, snapshot_id)?;
+    let mut players: Vec<PlayerFile> = read_manifest(backend, snapshot_id)?
+        .files
+        .iter()
+        .filter_map(|entry| {
+            let (folder, file) = entry.path.rsplit_once('/')?;
+            let world = match folder.rsplit_once('/') {
+                Some((world, "playerdata")) => world,
+                None if folder == "playerdata" => "",
+                _ => return None,
+            };
+            let uuid = file.strip_suffix(".dat")?;
+            Some(PlayerFile {
+                world: world.to_string(),
+                uuid: uuid.to_string(),
+                name: names.get(&uuid.to_lowercase()).cloned(),
+            })
+        })
+        .collect();
+    players.sort_by_key(|player| (player.to_string().to_lowercase(), player.world.clone()));
+    Ok(players)
+}
+
+/// The player's state in each of `snapshots` that has their file, in the same order.
+/// A file that cannot be read only leaves its snapshot out, with the reason in `warnings`.
+pub fn player_history(
+    backend: &dyn StorageBackend,
+    snapshots: &[String],
+    player: &PlayerFile,
+    warnings: &mut Vec<String>,
+) -> io::Result<Vec<PlayerState>> {
+    let path = player.path();
+    let mut history = Vec::new();
+    for snapshot_id in snapshots {
+        let key = join_key(&[snapshot_id, &path]);
+        if backend.stat(&key)?.is_none() {
+            continue;
+        }
+        let mut data = Vec::new();
+        let tag = backend
+            .get(&key, &mut data)
+            .and_then(|_| nbt::parse_gzip(&data).map_err(io::Error::other));
+        match tag {
+            Ok(tag) => history.push(read_state(snapshot_id, &tag)),
+            Err(e) => {
+                warn!("Could not read {} in {}: {}", path, snapshot_id, e);
+                warnings.push(format!("{}: {}", snapshot_id, e));
+            }
+        }
+    }
+    Ok(history)
+}
+
+fn read_state(snapshot_id: &str, tag: &Tag) -> PlayerState {
+    let items = |key: &str| -> Vec<String> {
+        tag.get(key)
+            .and_then(Tag::as_list)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(describe_item)
+            .collect()
+    };
+    let mut inventory = items("Inventory");
+    // Since 1.21.5 armour and the off-hand item are stored apart from the inventory
+    if let Some(Tag::Compound(equipment)) = tag.get("equipment") {
+        inventory.extend(equipment.values().filter_map(describe_item));
+    }
+
+    let position = tag.get("Pos").and_then(Tag::as_list).and_then(|pos| {
+        match pos.iter().map(Tag::as_f64).collect::<Option<Vec<_>>>()?[..] {
+            [x, y, z] => Some([x, y, z]),
+            _ => None,
+        }
+    });
+    // Before 1.16 the dimension was a number
+    let dimension = match tag.get("Dimension") {
+        Some(Tag::String(name)) => name.trim_start_matches("minecraft:").to_string(),
+        Some(number) => match number.as_i64() {
+            Some(-1) => "the_nether".to_string(),
+            Some(1) => "the_end".to_string(),
+            _ => "overworld".to_string(),
+        },
+        None => "overworld".to_string(),
+    };
+
+    PlayerState {
+        snapshot_id: snapshot_id.to_string(),
+        inventory,
+        ender_items: items("EnderItems"),
+        position,
+        dimension,
+        xp_level: tag.get("XpLevel").and_then(Tag::as_i64).unwrap_or(0),
+    }
+}
+
+/// `12 diamond`, or `diamond_sword (enchanted)`. Before 1.20.5 the count was `Count`
+/// and enchantments sat under `tag`; since then they are `count` and `components`.
+fn describe_item(item: &Tag) -> Option<String> {
+    let id = item.get("id")?.as_str()?.trim_start_matches("minecraft:");
+    let count = item
+        .get("count")
+        .or_else(|| item.get("Count"))
+        .and_then(Tag::as_i64)
+        .unwrap_or(1);
+    let enchanted = item
+        .get("components")
+        .and_then(|components| components.get("minecraft:enchantments"))
+        .or_else(|| item.get("tag").and_then(|tag| tag.get("Enchantments")))
+        .is_some();
+
+    let mut description = if count == 1 {
+        id.to_string()
+    } else {
+        format!("{} {}", count, id)
+    };
+    if enchanted {
+        description.push_str(" (enchanted)");
+    }
+    Some(description)
+}
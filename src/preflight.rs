@@ -0,0 +1,153 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+use crate::config::{Config, DestinationKind};
+use crate::errors::{self, Cause};
+use crate::file_operations;
+use crate::runs;
+use crate::storage::backend::StorageBackend;
+
+/// Written and removed again to check the destination accepts new files.
+const WRITE_TEST_KEY: &str = ".rustcraft-write-test";
+
+/// Room kept free on top of the estimate, as the world may grow while it is copied.
+const MARGIN_PERCENT: u64 = 10;
+
+/// Check the destination can take a snapshot of `src` before anything is written.
+/// Each problem refuses the run as skipped, with the reason in the error.
+pub fn check(config: &Config, src: &Path, backend: &dyn StorageBackend) -> io::Result<()> {
+    check_not_inside_source(config, src)?;
+    check_writable(backend)?;
+    check_free_space(src, backend)
+}
+
+// A destination inside the source would back up every earlier backup along with the world
+fn check_not_inside_source(config: &Config, src: &Path) -> io::Result<()> {
+    let Some(destination) = config
+        .backup_directory
+        .as_ref()
+        .filter(|_| config.destination == DestinationKind::Local)
+    else {
+        return Ok(());
+    };
+    let destination = resolve(Path::new(destination));
+    let src = resolve(src);
+    if destination == src {
+        return Err(errors::skipped_because(
+            Cause::DestinationInsideSource,
+            format!(
+                "The backup folder {} is the Minecraft folder itself.",
+                destination.display()
+            ),
+        ));
+    }
+    if destination.starts_with(&src) {
+        return Err(errors::skipped_because(
+            Cause::DestinationInsideSource,
+            format!(
+                "The backup folder {} is inside the Minecraft folder {}.",
+                destination.display(),
+                src.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Canonical form of a path that may not exist yet: its nearest existing ancestor is
+// resolved, so links and `..` cannot hide that two paths are the same
+fn resolve(path: &Path) -> PathBuf {
+    let mut missing = Vec::new();
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |path: PathBuf, part| path.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_owned());
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+fn check_writable(backend: &dyn StorageBackend) -> io::Result<()> {
+    backend
+        .put(WRITE_TEST_KEY, &mut io::empty())
+        .and_then(|_| backend.delete(WRITE_TEST_KEY))
+        .map_err(|e| {
+            errors::skipped(format!(
+                "Cannot write to the destination {}: {}",
+                backend.describe(),
+                e
+            ))
+        })
+}
+
+fn check_free_space(src: &Path, backend: &dyn StorageBackend) -> io::Result<()> {
+    // Object storage has no free space to speak of
+    let Some(available) = backend.available_space()? else {
+        return Ok(());
+    };
+    let estimate = directory_size(src)? * stored_ratio(backend) / 100;
+    let needed = estimate + estimate * MARGIN_PERCENT / 100;
+    info!(
+        "Snapshot estimated at {}, {} free in the destination",
+        runs::format_size(estimate),
+        runs::format_size(available)
+    );
+    if needed > available {
+        return Err(errors::skipped_because(
+            Cause::NotEnoughSpace,
+            format!(
+                "Not enough free space in {}: the snapshot needs about {}, only {} is free.",
+                backend.describe(),
+                runs::format_size(needed),
+                runs::format_size(available)
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// Size of the previous snapshot in the destination, in percent of the files it holds.
+// Snapshots are full copies, so this is a little over 100 for the manifest and map
+// preview; without a previous snapshot it is taken to be 100.
+fn stored_ratio(backend: &dyn StorageBackend) -> u64 {
+    let ratio = || -> io::Result<Option<u64>> {
+        let Some(previous) = file_operations::list_snapshots(backend)?.pop() else {
+            return Ok(None);
+        };
+        let manifest = file_operations::read_manifest(backend, &previous)?;
+        let files: u64 = manifest.files.iter().map(|entry| entry.size).sum();
+        let stored: u64 = backend
+            .list(&previous)?
+            .iter()
+            .map(|object| object.size)
+            .sum();
+        Ok((files > 0).then(|| stored * 100 / files))
+    };
+    ratio().ok().flatten().unwrap_or(100)
+}
+
+fn directory_size(dir: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            directory_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
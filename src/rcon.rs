@@ -0,0 +1,141 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use tracing::warn;
+
+use crate::config::RconSettings;
+use crate::errors::{self, Cause};
+
+const PACKET_RESPONSE: i32 = 0;
+const PACKET_COMMAND: i32 = 2;
+const PACKET_LOGIN: i32 = 3;
+
+/// An authenticated connection to a server's RCON port.
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+}
+
+impl RconClient {
+    pub fn connect(settings: &RconSettings) -> io::Result<Self> {
+        if settings.host.is_empty() || settings.password.is_empty() {
+            return Err(errors::known(
+                Cause::Rcon,
+                "RCON host and password must be configured",
+            ));
+        }
+        let stream = TcpStream::connect((settings.host.as_str(), settings.port.unwrap_or(25575)))?;
+        // `save-all flush` only answers once the world is on disk, which can take a while
+        stream.set_read_timeout(Some(Duration::from_secs(
+            settings.timeout_seconds.unwrap_or(120),
+        )))?;
+
+        let mut client = Self { stream, next_id: 1 };
+        let id = client.send(PACKET_LOGIN, &settings.password)?;
+        // Some servers send an empty response packet before the login result
+        loop {
+            let (response_id, kind, _) = client.receive()?;
+            if kind != PACKET_COMMAND {
+                continue;
+            }
+            if response_id == -1 {
+                return Err(errors::known(
+                    Cause::Rcon,
+                    format!("RCON authentication failed for {}", settings.host),
+                ));
+            }
+            if response_id == id {
+                return Ok(client);
+            }
+        }
+    }
+
+    /// Run a console command and return what the server answered.
+    pub fn command(&mut self, command: &str) -> io::Result<String> {
+        let id = self.send(PACKET_COMMAND, command)?;
+        loop {
+            let (response_id, kind, body) = self.receive()?;
+            if response_id == id && kind == PACKET_RESPONSE {
+                return Ok(body);
+            }
+        }
+    }
+
+    fn send(&mut self, kind: i32, body: &str) -> io::Result<i32> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut packet = Vec::with_capacity(14 + body.len());
+        packet.extend_from_slice(&(10 + body.len() as i32).to_le_bytes());
+        packet.extend_from_slice(&id.to_le_bytes());
+        packet.extend_from_slice(&kind.to_le_bytes());
+        packet.extend_from_slice(body.as_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        self.stream.write_all(&packet)?;
+        Ok(id)
+    }
+
+    fn receive(&mut self) -> io::Result<(i32, i32, String)> {
+        let mut header = [0u8; 4];
+        self.stream.read_exact(&mut header)?;
+        let length = i32::from_le_bytes(header);
+        if !(10..=4096 + 10).contains(&length) {
+            return Err(io::Error::other(format!(
+                "Invalid RCON packet length {}",
+                length
+            )));
+        }
+
+        let mut packet = vec![0u8; length as usize];
+        self.stream.read_exact(&mut packet)?;
+        let id = i32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]]);
+        let kind = i32::from_le_bytes([packet[4], packet[5], packet[6], packet[7]]);
+        let body = String::from_utf8_lossy(&packet[8..packet.len() - 2]).into_owned();
+        Ok((id, kind, body))
+    }
+}
+
+/// Run `backup` while the server has saving turned off, so the world does not change
+/// under the copy. `save-on` is sent whatever happens, reconnecting if the connection
+/// dropped during the backup.
+pub fn with_saving_paused<T>(
+    settings: &RconSettings,
+    backup: impl FnOnce() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut rcon = RconClient::connect(settings)?;
+    if settings.announce {
+        rcon.command("say Backing up the world, saving is paused")?;
+    }
+
+    let result = rcon
+        .command("save-off")
+        .and_then(|_| rcon.command("save-all flush"))
+        .and_then(|_| backup());
+
+    let resumed = rcon.command("save-on").or_else(|_| {
+        rcon = RconClient::connect(settings)?;
+        rcon.command("save-on")
+    });
+
+    if settings.announce && resumed.is_ok() {
+        let announcement = if result.is_ok() {
+            "say Backup complete, saving is back on"
+        } else {
+            "say Backup failed, saving is back on"
+        };
+        if let Err(e) = rcon.command(announcement) {
+            warn!("Could not announce the end of the backup: {}", e);
+        }
+    }
+
+    match resumed {
+        Ok(_) => result,
+        Err(e) => Err(io::Error::other(format!(
+            "Could not turn saving back on, run save-on on the server: {}",
+            e
+        ))),
+    }
+}
@@ -0,0 +1,582 @@
+use chrono::Utc;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use std::{
+    cell::RefCell,
+    fmt, fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::file_operations::read_manifest;
+use crate::nbt::{self, Endian};
+use crate::storage::backend::{join_key, StorageBackend};
+
+const SECTOR: usize = 4096;
+const CHUNKS: usize = 1024;
+
+/// Result of checking region files.
+#[derive(Debug, Default)]
+pub struct RegionReport {
+    pub regions: usize,
+    pub chunks: usize,
+    pub problems: Vec<String>,
+}
+
+impl RegionReport {
+    /// Short description for the UI, listing the first few problems.
+    pub fn summary(&self) -> String {
+        if self.problems.is_empty() {
+            return format!("{} chunks in {} region files OK", self.chunks, self.regions);
+        }
+        let shown: Vec<&str> = self.problems.iter().take(3).map(String::as_str).collect();
+        format!(
+            "{} problems in {} region files: {}{}",
+            self.problems.len(),
+            self.regions,
+            shown.join("; "),
+            if self.problems.len() > shown.len() {
+                "; ..."
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// Whether a file is an Anvil region file. `entities/` and `poi/` use the same format.
+pub fn is_region_file(path: &str) -> bool {
+    path.ends_with(".mca")
+}
+
+/// Check every region file below `dir`.
+pub fn check_directory(dir: &Path) -> io::Result<RegionReport> {
+    let mut report = RegionReport::default();
+    check_directory_into(dir, dir, &mut report)?;
+    Ok(report)
+}
+
+fn check_directory_into(base: &Path, dir: &Path, report: &mut RegionReport) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            check_directory_into(base, &path, report)?;
+        } else if is_region_file(&path.to_string_lossy()) {
+            let name = path.strip_prefix(base).unwrap().display().to_string();
+            let folder = path.parent().unwrap_or(base).to_path_buf();
+            let data = fs::read(&path)?;
+            check_region(&name, &data, report, |file| fs::read(folder.join(file)));
+        }
+    }
+    Ok(())
+}
+
+/// Check every region file of a snapshot, reading them back from the destination.
+pub fn check_snapshot(backend: &dyn StorageBackend, snapshot_id: &str) -> io::Result<RegionReport> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let mut report = RegionReport::default();
+    for entry in manifest
+        .files
+        .iter()
+        .filter(|entry| is_region_file(&entry.path))
+    {
+        let mut data = Vec::new();
+        backend.get(&join_key(&[snapshot_id, &entry.path]), &mut data)?;
+        let folder = entry.path.rsplit_once('/').map_or("", |(folder, _)| folder);
+        check_region(&entry.path, &data, &mut report, |file| {
+            let mut external = Vec::new();
+            backend.get(&join_key(&[snapshot_id, folder, file]), &mut external)?;
+            Ok(external)
+        });
+    }
+    Ok(report)
+}
+
+/// Region coordinates from a file name like `r.-1.2.mca`.
+pub fn region_coordinates(name: &str) -> Option<(i32, i32)> {
+    let file = name.rsplit(['/', '\\']).next()?;
+    let mut parts = file.strip_prefix("r.")?.strip_suffix(".mca")?.split('.');
+    let x = parts.next()?.parse().ok()?;
+    let z = parts.next()?.parse().ok()?;
+    Some((x, z))
+}
+
+/// A world's dimensions, which each keep their own region files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Dimension {
+    #[default]
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    pub const ALL: [Dimension; 3] = [Dimension::Overworld, Dimension::Nether, Dimension::End];
+
+    /// Folder of the dimension inside the world, empty for the overworld.
+    pub fn folder(self) -> &'static str {
+        match self {
+            Dimension::Overworld => "",
+            Dimension::Nether => "DIM-1",
+            Dimension::End => "DIM1",
+        }
+    }
+}
+
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Dimension::Overworld => "Overworld",
+            Dimension::Nether => "Nether",
+            Dimension::End => "End",
+        })
+    }
+}
+
+/// Folders that hold region files for a dimension: terrain, then entities and points of
+/// interest, which share the terrain's chunk grid.
+pub const REGION_FOLDERS: [&str; 3] = ["region", "entities", "poi"];
+
+/// Position of a chunk in chunk coordinates (block coordinates divided by 16).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkPosition {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkPosition {
+    pub fn from_block(x: i32, z: i32) -> Self {
+        Self {
+            x: x >> 4,
+            z: z >> 4,
+        }
+    }
+
+    /// Name of the region file holding this chunk.
+    pub fn region_file(self) -> String {
+        format!("r.{}.{}.mca", self.x >> 5, self.z >> 5)
+    }
+
+    fn index(self) -> usize {
+        (self.x & 31) as usize + (self.z & 31) as usize * 32
+    }
+}
+
+/// A chunk exactly as a region file stores it, so it can be written into another one.
+pub struct StoredChunk {
+    /// Length, compression type and compressed data.
+    data: Vec<u8>,
+    /// Contents of the `.mcc` file for chunks too large for the region file.
+    external: Option<Vec<u8>>,
+}
+
+/// Take a chunk out of a region file, if it is there and decodes correctly.
+/// Returns `Ok(None)` for chunks that were never generated.
+fn read_stored_chunk(
+    region_data: &[u8],
+    chunk: ChunkPosition,
+    read_external: impl Fn(&str) -> io::Result<Vec<u8>>,
+) -> Result<Option<StoredChunk>, String> {
+    if region_data.len() < 2 * SECTOR {
+        return Err("region header is truncated".to_string());
+    }
+    let index = chunk.index();
+    let location = &region_data[index * 4..index * 4 + 4];
+    let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+    let sectors = location[3] as usize;
+    if offset == 0 && sectors == 0 {
+        return Ok(None);
+    }
+    if offset < 2 || offset * SECTOR >= region_data.len() {
+        return Err(format!("invalid location (sector {})", offset));
+    }
+
+    let sector_data =
+        &region_data[offset * SECTOR..((offset + sectors) * SECTOR).min(region_data.len())];
+    let region = (chunk.x >> 5, chunk.z >> 5);
+    let external = RefCell::new(None);
+    decode_chunk(
+        sector_data,
+        (chunk.x & 31) as usize,
+        (chunk.z & 31) as usize,
+        Some(region),
+        |file| {
+            let data = read_external(file)?;
+            *external.borrow_mut() = Some(data.clone());
+            Ok(data)
+        },
+    )?;
+
+    let length = u32::from_be_bytes(sector_data[..4].try_into().unwrap()) as usize;
+    Ok(Some(StoredChunk {
+        data: sector_data[..4 + length].to_vec(),
+        external: external.into_inner(),
+    }))
+}
+
+/// Write `stored` into the region file at `region_path` in place of whatever the file held
+/// for that chunk. The chunk is appended, so a failed write never damages other chunks.
+fn write_chunk(region_path: &Path, chunk: ChunkPosition, stored: &StoredChunk) -> io::Result<()> {
+    let folder = region_path.parent().unwrap_or(Path::new("."));
+    if let Some(external) = &stored.external {
+        fs::write(
+            folder.join(format!("c.{}.{}.mcc", chunk.x, chunk.z)),
+            external,
+        )?;
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(region_path)?;
+    let length = file.metadata()?.len() as usize;
+    // Region files are made of whole sectors, starting with the two header sectors
+    let offset = length.div_ceil(SECTOR).max(2);
+    let sectors = stored.data.len().div_ceil(SECTOR);
+    if sectors > 255 {
+        return Err(io::Error::other("Chunk is too large for a region file"));
+    }
+
+    let mut padded = stored.data.clone();
+    padded.resize(sectors * SECTOR, 0);
+    file.seek(SeekFrom::Start((offset * SECTOR) as u64))?;
+    file.write_all(&padded)?;
+    file.sync_data()?;
+
+    // Point the header at the new copy only once it is on disk
+    let index = chunk.index();
+    let location = ((offset as u32) << 8) | sectors as u32;
+    file.seek(SeekFrom::Start((index * 4) as u64))?;
+    file.write_all(&location.to_be_bytes())?;
+    file.seek(SeekFrom::Start((SECTOR + index * 4) as u64))?;
+    file.write_all(&(Utc::now().timestamp() as u32).to_be_bytes())?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Number of chunks whose stored data differs between two versions of a region file.
+/// Chunks that only moved inside the file or were saved unchanged do not count.
+pub fn changed_chunks(old: &[u8], new: &[u8]) -> usize {
+    (0..CHUNKS)
+        .filter(|&index| chunk_bytes(old, index) != chunk_bytes(new, index))
+        .count()
+}
+
+/// Call `f` with every chunk of a region file that decodes correctly. Chunks stored in
+/// external `.mcc` files are skipped.
+pub fn for_each_chunk(
+    region: (i32, i32),
+    data: &[u8],
+    mut f: impl FnMut(ChunkPosition, &nbt::Tag),
+) {
+    for index in 0..CHUNKS {
+        let (local_x, local_z) = (index % 32, index / 32);
+        let Some(bytes) = chunk_bytes(data, index) else {
+            continue;
+        };
+        let external = |_: &str| Err(io::Error::other("not loaded"));
+        if let Ok(chunk) = decode_chunk(bytes, local_x, local_z, Some(region), external) {
+            let position = ChunkPosition {
+                x: region.0 * 32 + local_x as i32,
+                z: region.1 * 32 + local_z as i32,
+            };
+            f(position, &chunk);
+        }
+    }
+}
+
+/// The stored bytes of a chunk (length, compression and data), if the header points at any.
+fn chunk_bytes(data: &[u8], index: usize) -> Option<&[u8]> {
+    let location = data.get(index * 4..index * 4 + 4)?;
+    let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+    if offset < 2 {
+        return None;
+    }
+    let start = offset * SECTOR;
+    let length = u32::from_be_bytes(data.get(start..start + 4)?.try_into().unwrap()) as usize;
+    data.get(start..start + 4 + length)
+}
+
+/// The newest good copy of a chunk found in the backup history.
+pub struct RecoveredChunk {
+    pub snapshot_id: String,
+    /// Newer snapshots whose copy was damaged, or that did not have the chunk.
+    pub skipped: usize,
+    /// Copies to write back, by region folder.
+    parts: Vec<(&'static str, StoredChunk)>,
+}
+
+/// Search `snapshots`, newest first, for the most recent one where `chunk` of `world`
+/// decodes correctly. Entities and points of interest are taken from the same snapshot.
+pub fn find_good_chunk(
+    backend: &dyn StorageBackend,
+    snapshots: &[String],
+    world: &Path,
+    dimension: Dimension,
+    chunk: ChunkPosition,
+) -> io::Result<Option<RecoveredChunk>> {
+    for (skipped, snapshot_id) in snapshots.iter().enumerate() {
+        let manifest = read_manifest(backend, snapshot_id)?;
+        // Snapshots of other folders cannot hold this world
+        let Ok(relative) = world.strip_prefix(&manifest.source) else {
+            continue;
+        };
+        let prefix = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let mut parts = Vec::new();
+        for folder in REGION_FOLDERS {
+            let path = join_key(&[&prefix, dimension.folder(), folder, &chunk.region_file()]);
+            if !manifest.files.iter().any(|entry| entry.path == path) {
+                continue;
+            }
+            let mut data = Vec::new();
+            backend.get(&join_key(&[snapshot_id, &path]), &mut data)?;
+            let stored = read_stored_chunk(&data, chunk, |file| {
+                let mut external = Vec::new();
+                backend.get(
+                    &join_key(&[snapshot_id, &prefix, dimension.folder(), folder, file]),
+                    &mut external,
+                )?;
+                Ok(external)
+            });
+            if let Ok(Some(stored)) = stored {
+                parts.push((folder, stored));
+            }
+        }
+
+        if parts.first().is_some_and(|(folder, _)| *folder == "region") {
+            return Ok(Some(RecoveredChunk {
+                snapshot_id: snapshot_id.clone(),
+                skipped,
+                parts,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Write a recovered chunk back into the live world's region files.
+pub fn restore_chunk(
+    world: &Path,
+    dimension: Dimension,
+    chunk: ChunkPosition,
+    recovered: &RecoveredChunk,
+) -> io::Result<()> {
+    for (folder, stored) in &recovered.parts {
+        let mut region_folder: PathBuf = world.join(dimension.folder());
+        region_folder.push(folder);
+        fs::create_dir_all(&region_folder)?;
+        write_chunk(&region_folder.join(chunk.region_file()), chunk, stored)?;
+    }
+    Ok(())
+}
+
+/// Check the header and every chunk of one region file, adding what is wrong to `report`.
+/// `read_external` loads the `.mcc` files that hold chunks too large for the region file.
+fn check_region(
+    name: &str,
+    data: &[u8],
+    report: &mut RegionReport,
+    read_external: impl Fn(&str) -> io::Result<Vec<u8>>,
+) {
+    report.regions += 1;
+    // Files Minecraft has created but not written to yet are empty
+    if data.is_empty() {
+        return;
+    }
+    if data.len() < 2 * SECTOR {
+        report.problems.push(format!(
+            "{}: header is truncated ({} bytes)",
+            name,
+            data.len()
+        ));
+        return;
+    }
+
+    let region = region_coordinates(name);
+    let now = Utc::now().timestamp();
+    let total_sectors = data.len().div_ceil(SECTOR);
+    // Which chunk owns each sector, to find chunks that overlap
+    let mut owners: Vec<Option<usize>> = vec![None; total_sectors];
+
+    for index in 0..CHUNKS {
+        let (local_x, local_z) = (index % 32, index / 32);
+        let chunk_name = match region {
+            Some((x, z)) => format!(
+                "{}: chunk {}, {}",
+                name,
+                x * 32 + local_x as i32,
+                z * 32 + local_z as i32
+            ),
+            None => format!("{}: chunk {}, {} in the region", name, local_x, local_z),
+        };
+        let location = &data[index * 4..index * 4 + 4];
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize;
+        let sectors = location[3] as usize;
+        let timestamp = i32::from_be_bytes(
+            data[SECTOR + index * 4..SECTOR + index * 4 + 4]
+                .try_into()
+                .unwrap(),
+        ) as i64;
+
+        if offset == 0 && sectors == 0 {
+            continue;
+        }
+        report.chunks += 1;
+
+        // A zero timestamp is fine: external editors like MCEdit and Amulet write one
+        if timestamp > now + 24 * 3600 {
+            report
+                .problems
+                .push(format!("{}: timestamp is in the future", chunk_name));
+        }
+
+        if offset < 2 || sectors == 0 {
+            report.problems.push(format!(
+                "{}: invalid location (sector {}, {} sectors)",
+                chunk_name, offset, sectors
+            ));
+            continue;
+        }
+        if offset + sectors > total_sectors {
+            report.problems.push(format!(
+                "{}: is truncated, region file ends early",
+                chunk_name
+            ));
+            continue;
+        }
+
+        let mut overlaps = false;
+        for owner in &mut owners[offset..offset + sectors] {
+            match owner {
+                Some(other) => {
+                    if !overlaps {
+                        report.problems.push(format!(
+                            "{}: overlaps chunk {}, {} in the region",
+                            chunk_name,
+                            *other % 32,
+                            *other / 32
+                        ));
+                    }
+                    overlaps = true;
+                }
+                None => *owner = Some(index),
+            }
+        }
+
+        let sector_data = &data[offset * SECTOR..((offset + sectors) * SECTOR).min(data.len())];
+        if let Err(problem) = decode_chunk(sector_data, local_x, local_z, region, &read_external) {
+            report.problems.push(format!("{}: {}", chunk_name, problem));
+        }
+    }
+}
+
+/// Decompress and parse the chunk stored at the start of `sectors`.
+fn decode_chunk(
+    sectors: &[u8],
+    local_x: usize,
+    local_z: usize,
+    region: Option<(i32, i32)>,
+    read_external: impl Fn(&str) -> io::Result<Vec<u8>>,
+) -> Result<nbt::Tag, String> {
+    if sectors.len() < 5 {
+        return Err("chunk header is truncated".to_string());
+    }
+    let length = u32::from_be_bytes(sectors[..4].try_into().unwrap()) as usize;
+    if length == 0 {
+        return Err("chunk length is 0".to_string());
+    }
+    if length + 4 > sectors.len() {
+        return Err(format!(
+            "chunk length {} is longer than its {} sectors",
+            length,
+            sectors.len().div_ceil(SECTOR)
+        ));
+    }
+    let compression = sectors[4];
+    let external;
+    let payload = if compression & 128 != 0 {
+        // Oversized chunks live in `c.<x>.<z>.mcc` next to the region file
+        let (x, z) = region
+            .map(|(x, z)| (x * 32 + local_x as i32, z * 32 + local_z as i32))
+            .ok_or("chunk is stored externally, but the region file name is not standard")?;
+        external = read_external(&format!("c.{}.{}.mcc", x, z))
+            .map_err(|e| format!("external chunk file is unreadable: {}", e))?;
+        &external[..]
+    } else {
+        &sectors[5..4 + length]
+    };
+
+    let decompressed = match compression & 127 {
+        1 => read_all(GzDecoder::new(payload)),
+        2 => read_all(ZlibDecoder::new(payload)),
+        3 => Ok(payload.to_vec()),
+        4 => decompress_lz4(payload),
+        other => return Err(format!("unknown compression type {}", other)),
+    }
+    .map_err(|e| format!("does not decompress: {}", e))?;
+
+    let (_, chunk) = nbt::parse(&decompressed, Endian::Big)
+        .map_err(|e| format!("chunk data is damaged: {}", e))?;
+
+    // Chunks written for another position are as good as lost; before 1.18 they sit under `Level`
+    let position = chunk.get("Level").unwrap_or(&chunk);
+    if let (Some((x, z)), Some(chunk_x), Some(chunk_z)) = (
+        region,
+        position.get("xPos").and_then(|x| x.as_i64()),
+        position.get("zPos").and_then(|z| z.as_i64()),
+    ) {
+        let expected_x = (x * 32 + local_x as i32) as i64;
+        let expected_z = (z * 32 + local_z as i32) as i64;
+        if (chunk_x, chunk_z) != (expected_x, expected_z) {
+            return Err(format!("holds the data of chunk {}, {}", chunk_x, chunk_z));
+        }
+    }
+    Ok(chunk)
+}
+
+fn read_all(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Minecraft compresses with lz4-java's block stream: each block is `LZ4Block`, a method
+/// byte, compressed and decompressed lengths and a checksum, followed by the data.
+fn decompress_lz4(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut output = Vec::new();
+    loop {
+        if data.is_empty() {
+            return Ok(output);
+        }
+        if data.len() < 21 || &data[..8] != b"LZ4Block" {
+            return Err(invalid("invalid LZ4 block header"));
+        }
+        let method = data[8] & 0xf0;
+        let compressed = u32::from_le_bytes(data[9..13].try_into().unwrap()) as usize;
+        let decompressed = u32::from_le_bytes(data[13..17].try_into().unwrap()) as usize;
+        data = &data[21..];
+        // An empty block marks the end of the stream
+        if decompressed == 0 {
+            return Ok(output);
+        }
+        let block = data
+            .get(..compressed)
+            .ok_or_else(|| invalid("LZ4 block is truncated"))?;
+        match method {
+            0x10 => output.extend_from_slice(block),
+            0x20 => output.extend(
+                lz4_flex::block::decompress(block, decompressed)
+                    .map_err(|e| invalid(&e.to_string()))?,
+            ),
+            _ => return Err(invalid("unknown LZ4 block method")),
+        }
+        data = &data[compressed..];
+    }
+}
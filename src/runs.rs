@@ -0,0 +1,154 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::config;
+
+/// File in the data folder that every backup run appends one JSON line to.
+const RUNS_FILE: &str = "runs.jsonl";
+
+/// What started a backup run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    Manual,
+    Scheduled,
+}
+
+/// Everything known about one backup run, successful or not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub started: DateTime<Local>,
+    pub finished: DateTime<Local>,
+    pub trigger: Trigger,
+    /// Name of the backup profile the run belongs to. There is only one set of backup
+    /// settings so far, so this is always `None`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub source: String,
+    pub destination: String,
+    /// Names of the worlds in the snapshot.
+    #[serde(default)]
+    pub worlds: Vec<String>,
+    pub files: usize,
+    pub bytes: u64,
+    /// Bytes not written because the destination already held them. Snapshots are full
+    /// copies, so this is always 0.
+    #[serde(default)]
+    pub dedup_saved_bytes: u64,
+    /// Problems that did not stop the backup, like a map preview that failed to render.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    /// Why the run failed, `None` when it succeeded.
+    pub error: Option<String>,
+    /// The error followed by each of its causes.
+    #[serde(default)]
+    pub error_chain: Vec<String>,
+    /// The backup was refused on purpose, like for a full destination, rather than failing.
+    #[serde(default)]
+    pub skipped: bool,
+    pub snapshot_id: Option<String>,
+}
+
+impl RunRecord {
+    pub fn new(trigger: Trigger, destination: String) -> Self {
+        let now = Local::now();
+        Self {
+            started: now,
+            finished: now,
+            trigger,
+            profile: None,
+            source: String::new(),
+            destination,
+            worlds: Vec::new(),
+            files: 0,
+            bytes: 0,
+            dedup_saved_bytes: 0,
+            warnings: Vec::new(),
+            error: None,
+            error_chain: Vec::new(),
+            skipped: false,
+            snapshot_id: None,
+        }
+    }
+
+    /// One line for the history, like `12.05.2025 14:00 scheduled: 812 files, 45.1 MB in 12s`.
+    pub fn summary(&self) -> String {
+        let outcome = match &self.error {
+            Some(error) if self.skipped => format!("skipped: {}", error),
+            Some(error) => format!("failed: {}", error),
+            None => format!(
+                "{} files, {} in {}s",
+                self.files,
+                format_size(self.bytes),
+                (self.finished - self.started).num_seconds()
+            ),
+        };
+        let trigger = match self.trigger {
+            Trigger::Manual => "manual",
+            Trigger::Scheduled => "scheduled",
+        };
+        let mut summary = format!(
+            "{} {}: {}",
+            self.started.format("%d.%m.%Y %H:%M"),
+            trigger,
+            outcome
+        );
+        if !self.warnings.is_empty() {
+            summary.push_str(&format!(" ({} warnings)", self.warnings.len()));
+        }
+        summary
+    }
+}
+
+/// Sizes like `45.1 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+fn runs_file() -> PathBuf {
+    config::data_directory().join(RUNS_FILE)
+}
+
+/// Add a run to the end of the history log.
+pub fn append(record: &RunRecord) -> io::Result<()> {
+    let path = runs_file();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_vec(record)?;
+    line.push(b'\n');
+    // One write per line, so runs finishing at the same time do not interleave
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(&line)
+}
+
+/// Every recorded run, oldest first. Lines that cannot be read are skipped.
+pub fn load() -> io::Result<Vec<RunRecord>> {
+    match fs::read_to_string(runs_file()) {
+        Ok(data) => Ok(data
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,243 @@
+use serde::Deserialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, File},
+    io,
+    path::Path,
+};
+
+use crate::file_operations::{self, read_manifest};
+use crate::packages;
+use crate::storage::backend::{join_key, StorageBackend};
+
+/// Part of a snapshot that can be restored without touching the rest of the world.
+#[derive(Debug, Clone)]
+pub struct RestorePart {
+    /// What the user sees, like `world: Nether` or `world: player Steve`.
+    pub label: String,
+    /// World folder the part belongs to, relative to the snapshot root.
+    pub world: String,
+    /// Files and folders of the part, relative to the snapshot root. Folders end in `/`.
+    pub paths: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct UserCacheEntry {
+    name: String,
+    uuid: String,
+}
+
+/// Player names by UUID, from every `usercache.json` in a snapshot. Servers keep it next
+/// to the world and the game in `.minecraft`, so it may not be there at all.
+pub fn player_names(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+) -> io::Result<HashMap<String, String>> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let mut names = HashMap::new();
+    for entry in &manifest.files {
+        if entry.path != "usercache.json" && !entry.path.ends_with("/usercache.json") {
+            continue;
+        }
+        let mut data = Vec::new();
+        backend.get(&join_key(&[snapshot_id, &entry.path]), &mut data)?;
+        // A damaged cache only costs the names
+        let cache: Vec<UserCacheEntry> = serde_json::from_slice(&data).unwrap_or_default();
+        for user in cache {
+            names.insert(user.uuid.to_lowercase(), user.name);
+        }
+    }
+    Ok(names)
+}
+
+/// Dimensions, datapacks and player files of each Java world in a snapshot.
+/// Bedrock worlds keep all of that in one database, so they only restore as a whole.
+pub fn list_parts(backend: &dyn StorageBackend, snapshot_id: &str) -> io::Result<Vec<RestorePart>> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let names = player_names(backend, snapshot_id)?;
+    let mut parts = Vec::new();
+
+    for world in packages::snapshot_worlds(backend, snapshot_id)? {
+        if packages::snapshot_world_is_bedrock(backend, snapshot_id, &world)? {
+            continue;
+        }
+        let prefix = if world.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", world)
+        };
+        let world_name = if world.is_empty() { "world" } else { &world };
+        let files: Vec<&str> = manifest
+            .files
+            .iter()
+            .filter_map(|entry| entry.path.strip_prefix(&prefix))
+            .collect();
+        let has = |folder: &str| files.iter().any(|file| file.starts_with(folder));
+        let mut add = |label: String, folders: &[&str]| {
+            let paths: Vec<String> = folders
+                .iter()
+                .filter(|folder| has(folder))
+                .map(|folder| format!("{}{}", prefix, folder))
+                .collect();
+            if !paths.is_empty() {
+                parts.push(RestorePart {
+                    label: format!("{}: {}", world_name, label),
+                    world: world.clone(),
+                    paths,
+                });
+            }
+        };
+
+        add("Overworld".to_string(), &["region/", "entities/", "poi/"]);
+        add("Nether".to_string(), &["DIM-1/"]);
+        add("The End".to_string(), &["DIM1/"]);
+        // Custom dimensions live in dimensions/<namespace>/<name>/
+        let custom: BTreeSet<String> = files
+            .iter()
+            .filter_map(|file| {
+                let mut segments = file.split('/');
+                match (segments.next(), segments.next(), segments.next()) {
+                    (Some("dimensions"), Some(namespace), Some(name))
+                        if segments.next().is_some() =>
+                    {
+                        Some(format!("{}:{}", namespace, name))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        for dimension in custom {
+            let folder = format!("dimensions/{}/", dimension.replacen(':', "/", 1));
+            add(dimension, &[&folder]);
+        }
+        add("Datapacks".to_string(), &["datapacks/"]);
+
+        for file in &files {
+            let Some(uuid) = file
+                .strip_prefix("playerdata/")
+                .and_then(|file| file.strip_suffix(".dat"))
+            else {
+                continue;
+            };
+            let label = match names.get(&uuid.to_lowercase()) {
+                Some(name) => format!("player {}", name),
+                None => format!("player {}", uuid),
+            };
+            add(label, &[file]);
+        }
+    }
+    Ok(parts)
+}
+
+/// A part for any file or folder of a snapshot, as typed by the user.
+pub fn custom_part(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    path: &str,
+) -> io::Result<RestorePart> {
+    let path = path.trim().replace('\\', "/");
+    let path = path.trim_matches('/');
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let folder = format!("{}/", path);
+    let part_path = if manifest.files.iter().any(|entry| entry.path == path) {
+        path.to_string()
+    } else if !path.is_empty()
+        && manifest
+            .files
+            .iter()
+            .any(|entry| entry.path.starts_with(&folder))
+    {
+        folder
+    } else {
+        return Err(io::Error::other(format!(
+            "Snapshot {} has no file or folder \"{}\"",
+            snapshot_id, path
+        )));
+    };
+
+    // The world is the deepest folder with a level.dat above the path
+    let world = packages::snapshot_worlds(backend, snapshot_id)?
+        .into_iter()
+        .filter(|world| world.is_empty() || part_path.starts_with(&format!("{}/", world)))
+        .max_by_key(String::len)
+        .unwrap_or_default();
+    Ok(RestorePart {
+        label: path.to_string(),
+        world,
+        paths: vec![part_path],
+    })
+}
+
+/// Describe how `dst` differs from the folder a snapshot was taken from, or `None` when it
+/// is that same folder.
+pub fn target_mismatch(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    dst: &Path,
+) -> io::Result<Option<String>> {
+    let manifest = read_manifest(backend, snapshot_id)?;
+    let source = Path::new(&manifest.source);
+    // Either may be spelled differently, or no longer exist
+    let resolve = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    Ok((resolve(source) != resolve(dst)).then(|| {
+        format!(
+            "Snapshot {} was taken from {}, but the folder picked is {}.",
+            snapshot_id,
+            source.display(),
+            dst.display()
+        )
+    }))
+}
+
+/// Write the chosen parts of a snapshot into `dst`, which holds the snapshot's files at the
+/// same relative paths, like a full restore would. What the parts replace is first saved in
+/// a safety snapshot, whose id is returned.
+///
+/// Folders are replaced as a whole, so chunks generated after the snapshot do not survive
+/// next to the restored ones.
+pub fn restore_parts(
+    backend: &dyn StorageBackend,
+    snapshot_id: &str,
+    parts: &[RestorePart],
+    dst: &Path,
+) -> io::Result<String> {
+    let local_path = |relative: &str| file_operations::restore_path(dst, relative);
+
+    let manifest = read_manifest(backend, snapshot_id)?;
+    // Every path is checked before anything in `dst` is touched
+    let part_paths = parts.iter().flat_map(|part| &part.paths);
+    for path in manifest
+        .files
+        .iter()
+        .map(|entry| &entry.path)
+        .chain(part_paths)
+    {
+        local_path(path)?;
+    }
+
+    for part in parts {
+        file_operations::ensure_world_closed(&local_path(&part.world)?)?;
+    }
+    let replaced: Vec<String> = parts.iter().flat_map(|part| part.paths.clone()).collect();
+    let safety_snapshot = file_operations::take_safety_snapshot(dst, Some(&replaced), backend)?;
+
+    for path in parts.iter().flat_map(|part| &part.paths) {
+        if path.ends_with('/') {
+            let folder = local_path(path)?;
+            if folder.is_dir() {
+                fs::remove_dir_all(&folder)?;
+            }
+        }
+        for entry in manifest.files.iter().filter(|entry| {
+            entry.path == *path || (path.ends_with('/') && entry.path.starts_with(path.as_str()))
+        }) {
+            let destination_path = local_path(&entry.path)?;
+            if let Some(parent) = destination_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = File::create(&destination_path)?;
+            backend.get(&join_key(&[snapshot_id, &entry.path]), &mut file)?;
+        }
+    }
+    Ok(safety_snapshot)
+}
@@ -0,0 +1,203 @@
+use sha2::{Digest, Sha256};
+use ssh2::{CheckResult, KnownHostFileKind, Session, Sftp};
+use std::{
+    collections::HashSet,
+    fs, io,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tempfile::NamedTempFile;
+
+use crate::config::{self, SftpSettings};
+use crate::file_operations;
+
+/// Summary of a pull, for logging.
+#[derive(Debug, Default)]
+pub struct PullReport {
+    pub downloaded: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+}
+
+/// Open an authenticated SFTP channel using the given connection settings.
+pub fn connect(settings: &SftpSettings) -> io::Result<(Session, Sftp)> {
+    if settings.host.is_empty() || settings.username.is_empty() {
+        return Err(io::Error::other(
+            "SFTP host and username must be configured",
+        ));
+    }
+    let port = settings.port.unwrap_or(22);
+    let tcp = TcpStream::connect((settings.host.as_str(), port))?;
+    tcp.set_read_timeout(Some(Duration::from_secs(60)))?;
+
+    let mut session = Session::new()?;
+    session.set_tcp_stream(tcp);
+    session.handshake()?;
+    check_host_key(&session, settings, port)?;
+
+    if let Some(key) = &settings.private_key {
+        session.userauth_pubkey_file(
+            &settings.username,
+            None,
+            Path::new(key),
+            settings.private_key_passphrase.as_deref(),
+        )?;
+    } else if let Some(password) = &settings.password {
+        session.userauth_password(&settings.username, password)?;
+    } else {
+        session.userauth_agent(&settings.username)?;
+    }
+    if !session.authenticated() {
+        return Err(io::Error::other(format!(
+            "SFTP authentication failed for {}@{}",
+            settings.username, settings.host
+        )));
+    }
+
+    let sftp = session.sftp()?;
+    Ok((session, sftp))
+}
+
+/// Refuse to talk to a server whose key does not match `~/.ssh/known_hosts`.
+/// Unknown servers are only accepted when `trust_unknown_host` is set.
+fn check_host_key(session: &Session, settings: &SftpSettings, port: u16) -> io::Result<()> {
+    let (key, _) = session
+        .host_key()
+        .ok_or_else(|| io::Error::other("SFTP server did not send a host key"))?;
+
+    let mut known_hosts = session.known_hosts()?;
+    if let Some(file) = dirs::home_dir().map(|home| home.join(".ssh").join("known_hosts")) {
+        if file.exists() {
+            known_hosts.read_file(&file, KnownHostFileKind::OpenSSH)?;
+        }
+    }
+
+    match known_hosts.check_port(&settings.host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound if settings.trust_unknown_host => Ok(()),
+        CheckResult::NotFound => Err(io::Error::other(format!(
+            "Host key for {} is not in ~/.ssh/known_hosts. Connect once with ssh to trust it, or set trust_unknown_host",
+            settings.host
+        ))),
+        CheckResult::Mismatch => Err(io::Error::other(format!(
+            "Host key for {} does not match ~/.ssh/known_hosts. Refusing to connect",
+            settings.host
+        ))),
+        CheckResult::Failure => Err(io::Error::other(format!(
+            "Could not check the host key for {}",
+            settings.host
+        ))),
+    }
+}
+
+/// Folder a remote world is downloaded into before it is backed up. Each remote folder,
+/// told apart by user, host, port and path, gets its own subfolder, so mirroring never
+/// deletes anything RustCraft did not download for that same folder.
+pub fn staging_directory(configured: &Option<String>, settings: &SftpSettings) -> PathBuf {
+    let root = configured
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| config::data_directory().join("staging"));
+    let remote = format!(
+        "{}@{}:{}{}",
+        settings.username,
+        settings.host,
+        settings.port.unwrap_or(22),
+        settings.remote_path.trim_end_matches('/')
+    );
+    let readable: String = remote
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // Different folders can read the same once cleaned up, like `a_b` and `a/b`
+    let digest = Sha256::digest(remote.as_bytes());
+    let id = file_operations::to_hex(&digest[..4]);
+    root.join(format!("{}-{}", readable, id))
+}
+
+/// Mirror the configured remote directory into `staging`, downloading only files whose
+/// size or modification time changed since the last pull.
+pub fn pull_directory(settings: &SftpSettings, staging: &Path) -> io::Result<PullReport> {
+    let (_session, sftp) = connect(settings)?;
+    fs::create_dir_all(staging)?;
+
+    let mut report = PullReport::default();
+    let mut seen = HashSet::new();
+    let remote_root = settings.remote_path.trim_end_matches('/');
+    pull_recursively(&sftp, remote_root, staging, &mut seen, &mut report)?;
+    remove_stale(staging, &seen, &mut report)?;
+    Ok(report)
+}
+
+fn pull_recursively(
+    sftp: &Sftp,
+    remote_dir: &str,
+    local_dir: &Path,
+    seen: &mut HashSet<PathBuf>,
+    report: &mut PullReport,
+) -> io::Result<()> {
+    fs::create_dir_all(local_dir)?;
+    seen.insert(local_dir.to_path_buf());
+
+    for (path, stat) in sftp.readdir(Path::new(remote_dir))? {
+        // Build remote paths by hand: `Path::join` would use `\` on Windows
+        let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        let remote_path = format!("{}/{}", remote_dir, name);
+        let local_path = local_dir.join(&name);
+
+        if stat.is_dir() {
+            pull_recursively(sftp, &remote_path, &local_path, seen, report)?;
+        } else if stat.is_file() {
+            seen.insert(local_path.clone());
+            let remote_mtime = UNIX_EPOCH + Duration::from_secs(stat.mtime.unwrap_or(0));
+            if is_unchanged(&local_path, stat.size.unwrap_or(0), remote_mtime) {
+                report.unchanged += 1;
+                continue;
+            }
+
+            let mut remote = sftp.open(Path::new(&remote_path))?;
+            let mut temp = NamedTempFile::new_in(local_dir)?;
+            io::copy(&mut remote, &mut temp)?;
+            let file = temp.persist(&local_path).map_err(|e| e.error)?;
+            // Keep the remote timestamp, so the next pull can tell the file is unchanged
+            file.set_modified(remote_mtime)?;
+            report.downloaded += 1;
+        }
+    }
+    Ok(())
+}
+
+fn is_unchanged(local_path: &Path, remote_size: u64, remote_mtime: SystemTime) -> bool {
+    fs::metadata(local_path)
+        .map(|metadata| {
+            metadata.len() == remote_size && metadata.modified().ok() == Some(remote_mtime)
+        })
+        .unwrap_or(false)
+}
+
+/// Delete files and folders from the staging area that no longer exist on the server.
+fn remove_stale(dir: &Path, seen: &HashSet<PathBuf>, report: &mut PullReport) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !seen.contains(&path) {
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+            report.removed += 1;
+        } else if path.is_dir() {
+            remove_stale(&path, seen, report)?;
+        }
+    }
+    Ok(())
+}
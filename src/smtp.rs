@@ -0,0 +1,247 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Local;
+use rustls::{pki_types::ServerName, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::config::{SmtpSecurity, SmtpSettings};
+
+/// How long connecting, and then each read or write, may take before giving up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What an SMTP conversation runs over, before and after TLS.
+enum Connection {
+    Plain(TcpStream),
+    Tls(Box<StreamOwned<ClientConnection, TcpStream>>),
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.read(buf),
+            Connection::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Connection::Plain(stream) => stream.write(buf),
+            Connection::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Plain(stream) => stream.flush(),
+            Connection::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+/// A connection to a mail server, past the greeting and `EHLO`.
+struct SmtpClient {
+    stream: Connection,
+    /// Extensions the server announced in its last `EHLO` answer, like `STARTTLS` or `AUTH PLAIN`.
+    extensions: Vec<String>,
+}
+
+impl SmtpClient {
+    fn connect(settings: &SmtpSettings) -> io::Result<Self> {
+        if settings.host.is_empty() || settings.from.is_empty() || settings.to.is_empty() {
+            return Err(io::Error::other(
+                "SMTP host, sender and recipients must be configured",
+            ));
+        }
+        let port = settings.port.unwrap_or(match settings.security {
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::None => 25,
+        });
+        let tcp = connect(&settings.host, port)?;
+        tcp.set_read_timeout(Some(TIMEOUT))?;
+        tcp.set_write_timeout(Some(TIMEOUT))?;
+
+        let stream = match settings.security {
+            SmtpSecurity::Tls => wrap_tls(&settings.host, tcp)?,
+            SmtpSecurity::StartTls | SmtpSecurity::None => Connection::Plain(tcp),
+        };
+        let mut client = Self {
+            stream,
+            extensions: Vec::new(),
+        };
+        client.expect(220)?;
+        client.hello()?;
+
+        if settings.security == SmtpSecurity::StartTls {
+            if !client.supports("STARTTLS") {
+                return Err(io::Error::other(format!(
+                    "{} does not offer STARTTLS",
+                    settings.host
+                )));
+            }
+            client.command("STARTTLS", 220)?;
+            // Nothing is read past the 220, so the TCP stream can be handed over to TLS as is
+            if let Connection::Plain(tcp) = client.stream {
+                client = Self {
+                    stream: wrap_tls(&settings.host, tcp)?,
+                    extensions: Vec::new(),
+                };
+                client.hello()?;
+            }
+        }
+        Ok(client)
+    }
+
+    fn hello(&mut self) -> io::Result<()> {
+        let reply = self.command("EHLO rustcraft", 250)?;
+        // The first line is the server's greeting, the others one extension each
+        self.extensions = reply
+            .into_iter()
+            .skip(1)
+            .map(|line| line.to_uppercase())
+            .collect();
+        Ok(())
+    }
+
+    fn supports(&self, extension: &str) -> bool {
+        self.extensions
+            .iter()
+            .any(|line| line.split_whitespace().next() == Some(extension))
+    }
+
+    fn login(&mut self, username: &str, password: &str) -> io::Result<()> {
+        let plain = self
+            .extensions
+            .iter()
+            .any(|line| line.starts_with("AUTH") && line.split_whitespace().any(|m| m == "PLAIN"));
+        if plain {
+            let credentials = BASE64.encode(format!("\0{}\0{}", username, password));
+            self.command(&format!("AUTH PLAIN {}", credentials), 235)?;
+        } else {
+            self.command("AUTH LOGIN", 334)?;
+            self.command(&BASE64.encode(username), 334)?;
+            self.command(&BASE64.encode(password), 235)?;
+        }
+        Ok(())
+    }
+
+    /// Send one command and check the reply code, returning the reply's lines without codes.
+    fn command(&mut self, command: &str, expected: u16) -> io::Result<Vec<String>> {
+        self.stream.write_all(command.as_bytes())?;
+        self.stream.write_all(b"\r\n")?;
+        self.stream.flush()?;
+        self.expect(expected).map_err(|e| {
+            // Never put credentials in an error message
+            let verb = command.split_whitespace().next().unwrap_or_default();
+            let verb = if verb.starts_with("AUTH") || expected == 235 || expected == 334 {
+                "AUTH"
+            } else {
+                verb
+            };
+            io::Error::other(format!("SMTP {} failed: {}", verb, e))
+        })
+    }
+
+    fn expect(&mut self, expected: u16) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| io::Error::other(format!("Invalid SMTP reply: {}", line)))?;
+            lines.push(line.get(4..).unwrap_or_default().to_string());
+            // `250-` continues a multi-line reply, `250 ` ends it
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+            if code != expected {
+                return Err(io::Error::other(format!("server answered {}", line)));
+            }
+            return Ok(lines);
+        }
+    }
+
+    // Byte by byte, so nothing past the reply is read before a switch to TLS
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while !line.ends_with(b"\r\n") {
+            if self.stream.read(&mut byte)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "SMTP server closed the connection",
+                ));
+            }
+            line.push(byte[0]);
+        }
+        line.truncate(line.len() - 2);
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+// Try each address of `host` in turn, like `TcpStream::connect`, but never wait on one forever
+fn connect(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_error = None;
+    for address in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, TIMEOUT) {
+            Ok(tcp) => return Ok(tcp),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| io::Error::other(format!("{} has no addresses", host))))
+}
+
+fn wrap_tls(host: &str, tcp: TcpStream) -> io::Result<Connection> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let name = ServerName::try_from(host.to_string()).map_err(io::Error::other)?;
+    let connection = ClientConnection::new(Arc::new(config), name).map_err(io::Error::other)?;
+    Ok(Connection::Tls(Box::new(StreamOwned::new(connection, tcp))))
+}
+
+/// Send a plain text email to every configured recipient.
+pub fn send_mail(settings: &SmtpSettings, subject: &str, body: &str) -> io::Result<()> {
+    let mut client = SmtpClient::connect(settings)?;
+    if let Some(username) = settings.username.as_deref().filter(|name| !name.is_empty()) {
+        client.login(username, settings.password.as_deref().unwrap_or_default())?;
+    }
+
+    client.command(&format!("MAIL FROM:<{}>", settings.from), 250)?;
+    for recipient in &settings.to {
+        client.command(&format!("RCPT TO:<{}>", recipient), 250)?;
+    }
+    client.command("DATA", 354)?;
+
+    let mut message = format!(
+        "From: RustCraft <{}>\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\n\
+         MIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\n\
+         Content-Transfer-Encoding: 8bit\r\n\r\n",
+        settings.from,
+        settings.to.join(", "),
+        subject,
+        Local::now().to_rfc2822()
+    );
+    for line in body.lines() {
+        // A line starting with a dot gets another one, so it cannot end the message early
+        if line.starts_with('.') {
+            message.push('.');
+        }
+        message.push_str(line);
+        message.push_str("\r\n");
+    }
+    message.push('.');
+    client.command(&message, 250)?;
+    client.command("QUIT", 221)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::{
+    env,
+    io::{self, Read, Write},
+};
+
+use super::{
+    encrypted::EncryptedBackend, local::LocalBackend, s3::S3Backend, sftp::SftpBackend,
+    webdav::WebDavBackend,
+};
+use crate::config::{Config, DestinationKind};
+
+/// Metadata about a single object stored in a backend.
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    /// Key relative to the backend root, always using `/` as the separator.
+    pub key: String,
+    pub size: u64,
+}
+
+/// A place backups can be written to and read back from.
+///
+/// Keys are `/`-separated paths relative to the root of the backend, e.g.
+/// `"01.06.2024 12.00.00/New World/level.dat"`. Every backend must treat them
+/// the same way, so the snapshot engine never needs to know where the bytes end up.
+pub trait StorageBackend: Send + Sync {
+    /// Store everything read from `data` under `key`, replacing any existing object.
+    /// Returns the number of bytes written.
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64>;
+
+    /// Stream the object stored under `key` into `out`. Returns the number of bytes read.
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64>;
+
+    /// List every object below `prefix`, which is treated as a folder, recursively.
+    /// An empty prefix lists everything.
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>>;
+
+    /// Remove the object stored under `key`. Deleting a missing key is not an error.
+    fn delete(&self, key: &str) -> io::Result<()>;
+
+    /// Look up a single object, returning `None` if it does not exist.
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>>;
+
+    /// Human readable description of the destination, used in messages.
+    fn describe(&self) -> String;
+
+    /// Free space left in the destination in bytes, `None` where it cannot be known,
+    /// like for object storage.
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+/// Join key segments with `/`, skipping empty ones.
+pub fn join_key(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .flat_map(|part| part.split('/'))
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Open the destination selected in the configuration, or `None` if it has not been set up yet.
+/// When encryption is enabled the destination is unlocked with the passphrase first.
+pub fn open_destination(config: &Config) -> Option<io::Result<Box<dyn StorageBackend>>> {
+    let backend = open_unencrypted(config)?;
+    if !config.encryption.enabled {
+        return Some(backend);
+    }
+    let passphrase = config
+        .encryption
+        .passphrase
+        .clone()
+        .or_else(|| env::var("RUSTCRAFT_PASSPHRASE").ok())
+        .unwrap_or_default();
+    Some(backend.and_then(|backend| {
+        EncryptedBackend::open(backend, &passphrase)
+            .map(|backend| Box::new(backend) as Box<dyn StorageBackend>)
+    }))
+}
+
+/// Describe the configured destination the way its backend would, without connecting to it
+/// or unlocking it, so it can be shown on every redraw. `None` if it has not been set up yet.
+pub fn describe_destination(config: &Config) -> Option<String> {
+    let description = match config.destination {
+        DestinationKind::Local => config.backup_directory.clone()?,
+        DestinationKind::S3 => {
+            format!("s3://{}", join_key(&[&config.s3.bucket, &config.s3.prefix]))
+        }
+        DestinationKind::Sftp => format!(
+            "sftp://{}@{}{}",
+            config.sftp.username,
+            config.sftp.host,
+            config.sftp.remote_path.trim_end_matches('/')
+        ),
+        DestinationKind::WebDav => config.webdav.url.trim_end_matches('/').to_string(),
+    };
+    Some(if config.encryption.enabled {
+        format!("{} (encrypted)", description)
+    } else {
+        description
+    })
+}
+
+fn open_unencrypted(config: &Config) -> Option<io::Result<Box<dyn StorageBackend>>> {
+    match config.destination {
+        DestinationKind::Local => config
+            .backup_directory
+            .as_ref()
+            .map(|dir| Ok(Box::new(LocalBackend::new(dir)) as Box<dyn StorageBackend>)),
+        DestinationKind::S3 => Some(
+            S3Backend::new(&config.s3).map(|backend| Box::new(backend) as Box<dyn StorageBackend>),
+        ),
+        DestinationKind::Sftp => Some(
+            SftpBackend::new(&config.sftp)
+                .map(|backend| Box::new(backend) as Box<dyn StorageBackend>),
+        ),
+        DestinationKind::WebDav => Some(
+            WebDavBackend::new(&config.webdav)
+                .map(|backend| Box::new(backend) as Box<dyn StorageBackend>),
+        ),
+    }
+}
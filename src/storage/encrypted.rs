@@ -0,0 +1,363 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{
+        rand_core::RngCore,
+        stream::{DecryptorBE32, EncryptorBE32},
+        Aead, AeadCore, KeyInit, OsRng, Payload,
+    },
+    Key, XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+
+use super::backend::{ObjectInfo, StorageBackend};
+
+/// Stored unencrypted at the root of the destination. Holds the key derivation
+/// parameters and a known value encrypted with the key, so a wrong passphrase is
+/// caught before anything is written or restored.
+pub const KEY_FILE: &str = "encryption.json";
+
+const MAGIC: &[u8; 4] = b"RCE1";
+/// 24 byte XChaCha20 nonce, minus the 5 bytes the STREAM construction uses for its counter.
+const NONCE_PREFIX_LEN: usize = 19;
+const HEADER_LEN: usize = MAGIC.len() + NONCE_PREFIX_LEN;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const KEY_CHECK: &[u8] = b"RustCraft key check";
+
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    salt: String,
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+    /// Nonce followed by the encrypted `KEY_CHECK` value.
+    check: String,
+}
+
+/// Wraps another backend, encrypting every object with XChaCha20-Poly1305 before it
+/// leaves the machine. The key is derived from a passphrase with Argon2id.
+///
+/// Object names are left as they are; only their contents are encrypted. Each object
+/// is bound to its key, so encrypted files cannot be swapped around in the destination.
+pub struct EncryptedBackend {
+    inner: Box<dyn StorageBackend>,
+    key: Key,
+}
+
+impl EncryptedBackend {
+    /// Unlock the destination with `passphrase`, setting up encryption if this is the first
+    /// time it is used. Fails if the passphrase does not match the one used before.
+    pub fn open(inner: Box<dyn StorageBackend>, passphrase: &str) -> io::Result<Self> {
+        if passphrase.is_empty() {
+            return Err(io::Error::other(
+                "Encryption is enabled but no passphrase is set",
+            ));
+        }
+
+        let mut data = Vec::new();
+        let key = match inner.get(KEY_FILE, &mut data) {
+            Ok(_) => {
+                let key_file: KeyFile = serde_json::from_slice(&data).map_err(io::Error::other)?;
+                let key = derive_key(passphrase, &key_file)?;
+                let check = decode(&key_file.check)?;
+                if check.len() < 24 {
+                    return Err(io::Error::other("Damaged encryption key file"));
+                }
+                let (nonce, ciphertext) = check.split_at(24);
+                match XChaCha20Poly1305::new(&key).decrypt(XNonce::from_slice(nonce), ciphertext) {
+                    Ok(plaintext) if plaintext == KEY_CHECK => key,
+                    _ => {
+                        return Err(io::Error::other(format!(
+                            "Wrong passphrase for the encrypted backups in {}",
+                            inner.describe()
+                        )))
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                create_key_file(inner.as_ref(), passphrase)?
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self { inner, key })
+    }
+}
+
+fn argon2(key_file: &KeyFile) -> io::Result<Argon2<'static>> {
+    let params = Params::new(key_file.m_cost, key_file.t_cost, key_file.p_cost, Some(32))
+        .map_err(|e| io::Error::other(format!("Invalid key derivation parameters: {}", e)))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+}
+
+fn derive_key(passphrase: &str, key_file: &KeyFile) -> io::Result<Key> {
+    let salt = decode(&key_file.salt)?;
+    let mut key = Key::default();
+    argon2(key_file)?
+        .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+        .map_err(|e| io::Error::other(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+fn create_key_file(inner: &dyn StorageBackend, passphrase: &str) -> io::Result<Key> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut key_file = KeyFile {
+        version: 1,
+        salt: BASE64.encode(salt),
+        m_cost: Params::DEFAULT_M_COST,
+        t_cost: Params::DEFAULT_T_COST,
+        p_cost: Params::DEFAULT_P_COST,
+        check: String::new(),
+    };
+    let key = derive_key(passphrase, &key_file)?;
+
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = XChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, KEY_CHECK)
+        .map_err(|_| io::Error::other("Encryption failed"))?;
+    key_file.check = BASE64.encode([nonce.as_slice(), &ciphertext].concat());
+
+    let data = serde_json::to_vec_pretty(&key_file)?;
+    inner.put(KEY_FILE, &mut data.as_slice())?;
+    Ok(key)
+}
+
+fn decode(text: &str) -> io::Result<Vec<u8>> {
+    BASE64
+        .decode(text)
+        .map_err(|e| io::Error::other(format!("Damaged encryption key file: {}", e)))
+}
+
+/// Size of the original data, given the size of its encrypted form.
+fn plaintext_size(ciphertext_size: u64) -> u64 {
+    let body = ciphertext_size.saturating_sub(HEADER_LEN as u64);
+    let chunks = body.div_ceil((CHUNK_SIZE + TAG_LEN) as u64);
+    body.saturating_sub(chunks * TAG_LEN as u64)
+}
+
+impl StorageBackend for EncryptedBackend {
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64> {
+        let mut reader = EncryptingReader::new(&self.key, key, data);
+        self.inner.put(key, &mut reader)?;
+        Ok(reader.plaintext_len)
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64> {
+        let mut writer = DecryptingWriter::new(&self.key, key, out);
+        self.inner.get(key, &mut writer)?;
+        writer.finish()
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        Ok(self
+            .inner
+            .list(prefix)?
+            .into_iter()
+            .filter(|object| object.key != KEY_FILE)
+            .map(|object| ObjectInfo {
+                size: plaintext_size(object.size),
+                ..object
+            })
+            .collect())
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        self.inner.delete(key)
+    }
+
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>> {
+        Ok(self.inner.stat(key)?.map(|object| ObjectInfo {
+            size: plaintext_size(object.size),
+            ..object
+        }))
+    }
+
+    fn describe(&self) -> String {
+        format!("{} (encrypted)", self.inner.describe())
+    }
+
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        self.inner.available_space()
+    }
+}
+
+/// Produces the encrypted form of everything read from `inner`: a header with a random
+/// nonce prefix, then the data in authenticated chunks.
+struct EncryptingReader<'a> {
+    inner: &'a mut dyn Read,
+    encryptor: Option<EncryptorBE32<XChaCha20Poly1305>>,
+    aad: Vec<u8>,
+    /// Plaintext read ahead of the chunk being encrypted, to know which chunk is the last one.
+    pending: Option<Vec<u8>>,
+    output: Vec<u8>,
+    position: usize,
+    plaintext_len: u64,
+}
+
+impl<'a> EncryptingReader<'a> {
+    fn new(key: &Key, object_key: &str, inner: &'a mut dyn Read) -> Self {
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut nonce_prefix);
+        let encryptor =
+            EncryptorBE32::from_aead(XChaCha20Poly1305::new(key), (&nonce_prefix).into());
+        Self {
+            inner,
+            encryptor: Some(encryptor),
+            aad: object_key.as_bytes().to_vec(),
+            pending: None,
+            output: [MAGIC.as_slice(), &nonce_prefix].concat(),
+            position: 0,
+            plaintext_len: 0,
+        }
+    }
+
+    fn fill_chunk(&mut self) -> io::Result<Vec<u8>> {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        (&mut self.inner)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)?;
+        Ok(chunk)
+    }
+
+    /// Encrypt the next chunk into `output`. Returns false once everything has been encrypted.
+    fn encrypt_next(&mut self) -> io::Result<bool> {
+        if self.encryptor.is_none() {
+            return Ok(false);
+        }
+        let chunk = match self.pending.take() {
+            Some(chunk) => chunk,
+            None => self.fill_chunk()?,
+        };
+        self.plaintext_len += chunk.len() as u64;
+
+        let next = self.fill_chunk()?;
+        let payload = Payload {
+            msg: &chunk,
+            aad: &self.aad,
+        };
+        self.output = if next.is_empty() {
+            let encryptor = self.encryptor.take().unwrap();
+            encryptor.encrypt_last(payload)
+        } else {
+            self.encryptor.as_mut().unwrap().encrypt_next(payload)
+        }
+        .map_err(|_| io::Error::other("Encryption failed"))?;
+        self.position = 0;
+        self.pending = Some(next);
+        Ok(true)
+    }
+}
+
+impl Read for EncryptingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            if !self.encrypt_next()? {
+                return Ok(0);
+            }
+        }
+        let count = buf.len().min(self.output.len() - self.position);
+        buf[..count].copy_from_slice(&self.output[self.position..self.position + count]);
+        self.position += count;
+        Ok(count)
+    }
+}
+
+/// Checks and decrypts data written to it, passing the plaintext on to `out`.
+struct DecryptingWriter<'a> {
+    key: Key,
+    aad: Vec<u8>,
+    out: &'a mut dyn Write,
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+    written: u64,
+}
+
+impl<'a> DecryptingWriter<'a> {
+    fn new(key: &Key, object_key: &str, out: &'a mut dyn Write) -> Self {
+        Self {
+            key: *key,
+            aad: object_key.as_bytes().to_vec(),
+            out,
+            decryptor: None,
+            buffer: Vec::new(),
+            written: 0,
+        }
+    }
+
+    fn decryption_error(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} could not be decrypted: it is damaged, was not written by RustCraft, or the key is wrong",
+                String::from_utf8_lossy(&self.aad)
+            ),
+        )
+    }
+
+    /// Decrypt every chunk that is known not to be the last one.
+    fn process(&mut self) -> io::Result<()> {
+        if self.decryptor.is_none() {
+            if self.buffer.len() < HEADER_LEN {
+                return Ok(());
+            }
+            if &self.buffer[..MAGIC.len()] != MAGIC {
+                return Err(self.decryption_error());
+            }
+            let nonce_prefix = &self.buffer[MAGIC.len()..HEADER_LEN];
+            self.decryptor = Some(DecryptorBE32::from_aead(
+                XChaCha20Poly1305::new(&self.key),
+                nonce_prefix.into(),
+            ));
+            self.buffer.drain(..HEADER_LEN);
+        }
+
+        while self.buffer.len() > CHUNK_SIZE + TAG_LEN {
+            let chunk: Vec<u8> = self.buffer.drain(..CHUNK_SIZE + TAG_LEN).collect();
+            let plaintext = self
+                .decryptor
+                .as_mut()
+                .unwrap()
+                .decrypt_next(Payload {
+                    msg: &chunk,
+                    aad: &self.aad,
+                })
+                .map_err(|_| self.decryption_error())?;
+            self.out.write_all(&plaintext)?;
+            self.written += plaintext.len() as u64;
+        }
+        Ok(())
+    }
+
+    /// Decrypt the final chunk. Fails if the data was truncated.
+    fn finish(mut self) -> io::Result<u64> {
+        self.process()?;
+        let Some(decryptor) = self.decryptor.take() else {
+            return Err(self.decryption_error());
+        };
+        let plaintext = decryptor
+            .decrypt_last(Payload {
+                msg: &self.buffer,
+                aad: &self.aad,
+            })
+            .map_err(|_| self.decryption_error())?;
+        self.out.write_all(&plaintext)?;
+        self.out.flush()?;
+        Ok(self.written + plaintext.len() as u64)
+    }
+}
+
+impl Write for DecryptingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        self.process()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::NamedTempFile;
+
+use super::backend::{ObjectInfo, StorageBackend};
+
+/// Stores backups in a folder on a local or mounted disk.
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        key.split('/')
+            .filter(|segment| !segment.is_empty())
+            .fold(self.root.clone(), |path, segment| path.join(segment))
+    }
+
+    fn key_for(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn object_info(&self, path: &Path, metadata: &fs::Metadata) -> ObjectInfo {
+        ObjectInfo {
+            key: self.key_for(path),
+            size: metadata.len(),
+        }
+    }
+
+    fn list_recursively(&self, dir: &Path, objects: &mut Vec<ObjectInfo>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                self.list_recursively(&path, objects)?;
+            } else {
+                objects.push(self.object_info(&path, &entry.metadata()?));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for LocalBackend {
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64> {
+        let path = self.path_for(key);
+        let parent = path.parent().unwrap_or(&self.root);
+        fs::create_dir_all(parent)?;
+
+        // Write to a temporary file first so an interrupted backup never leaves
+        // a half-written file behind under the final name.
+        let mut temp = NamedTempFile::new_in(parent)?;
+        let written = io::copy(data, &mut temp)?;
+        temp.as_file().sync_all()?;
+        temp.persist(&path).map_err(|e| e.error)?;
+        Ok(written)
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64> {
+        let mut file = File::open(self.path_for(key))?;
+        io::copy(&mut file, out)
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        let start = self.path_for(prefix);
+        let mut objects = Vec::new();
+        if start.is_dir() {
+            self.list_recursively(&start, &mut objects)?;
+        } else if start.is_file() {
+            objects.push(self.object_info(&start, &fs::metadata(&start)?));
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        let path = self.path_for(key);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        // Clean up directories left empty, but never the root itself.
+        let mut dir = path.parent();
+        while let Some(current) = dir {
+            if current == self.root || !current.starts_with(&self.root) {
+                break;
+            }
+            if fs::remove_dir(current).is_err() {
+                break; // Not empty
+            }
+            dir = current.parent();
+        }
+        Ok(())
+    }
+
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>> {
+        let path = self.path_for(key);
+        match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => Ok(Some(self.object_info(&path, &metadata))),
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn available_space(&self) -> io::Result<Option<u64>> {
+        // The backup folder is only created by the first backup; ask the disk it will be on
+        let existing = self
+            .root
+            .ancestors()
+            .find(|path| path.exists())
+            .unwrap_or(&self.root);
+        fs4::available_space(existing).map(Some)
+    }
+}
@@ -0,0 +1,424 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use super::backend::{join_key, ObjectInfo, StorageBackend};
+use super::xml;
+use crate::config::S3Settings;
+use crate::file_operations::to_hex;
+
+/// Size of each part of a multipart upload. Files smaller than this are sent in a single PUT.
+/// S3 requires every part except the last to be at least 5 MiB.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+const EMPTY_PAYLOAD_SHA256: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Stores backups in an S3-compatible bucket, signing requests with AWS Signature Version 4.
+pub struct S3Backend {
+    agent: ureq::Agent,
+    scheme: String,
+    host: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+    path_style: bool,
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl S3Backend {
+    pub fn new(settings: &S3Settings) -> io::Result<Self> {
+        if settings.bucket.is_empty() {
+            return Err(io::Error::other("S3 bucket is not configured"));
+        }
+        let region = settings
+            .region
+            .clone()
+            .unwrap_or_else(|| "us-east-1".to_string());
+        let endpoint = settings
+            .endpoint
+            .clone()
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        let (scheme, host) = endpoint
+            .trim_end_matches('/')
+            .split_once("://")
+            .map(|(scheme, host)| (scheme.to_string(), host.to_string()))
+            .ok_or_else(|| io::Error::other(format!("Invalid S3 endpoint: {}", endpoint)))?;
+
+        // Credentials in the configuration take precedence over the environment
+        let access_key_id = settings
+            .access_key_id
+            .clone()
+            .or_else(|| env::var("AWS_ACCESS_KEY_ID").ok())
+            .ok_or_else(|| io::Error::other("S3 access key id is not configured"))?;
+        let secret_access_key = settings
+            .secret_access_key
+            .clone()
+            .or_else(|| env::var("AWS_SECRET_ACCESS_KEY").ok())
+            .ok_or_else(|| io::Error::other("S3 secret access key is not configured"))?;
+
+        Ok(Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(30))
+                .timeout_read(Duration::from_secs(300))
+                .timeout_write(Duration::from_secs(300))
+                .build(),
+            scheme,
+            host,
+            region,
+            bucket: settings.bucket.clone(),
+            prefix: join_key(&[&settings.prefix]),
+            path_style: settings.path_style,
+            access_key_id,
+            secret_access_key,
+            session_token: env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        join_key(&[&self.prefix, key])
+    }
+
+    /// Send a signed request for `object_key` (relative to the bucket, not the prefix).
+    fn send(
+        &self,
+        method: &str,
+        object_key: &str,
+        query: &[(&str, &str)],
+        body: &[u8],
+    ) -> io::Result<ureq::Response> {
+        let (host, path) = if self.path_style {
+            (self.host.clone(), format!("/{}", self.bucket))
+        } else {
+            (format!("{}.{}", self.bucket, self.host), String::new())
+        };
+        let canonical_uri = if object_key.is_empty() {
+            if path.is_empty() {
+                "/".to_string()
+            } else {
+                path
+            }
+        } else {
+            format!("{}/{}", path, uri_encode(object_key, false))
+        };
+
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let payload_hash = if body.is_empty() {
+            EMPTY_PAYLOAD_SHA256.to_string()
+        } else {
+            to_hex(&Sha256::digest(body))
+        };
+
+        let mut url = format!("{}://{}{}", self.scheme, host, canonical_uri);
+        if !canonical_query.is_empty() {
+            url = format!("{}?{}", url, canonical_query);
+        }
+
+        let mut request = self.agent.request(method, &url);
+        for (name, value) in self.sign(
+            method,
+            &host,
+            &canonical_uri,
+            &canonical_query,
+            &payload_hash,
+            Utc::now(),
+        ) {
+            request = request.set(&name, &value);
+        }
+
+        let result = if body.is_empty() {
+            request.call()
+        } else {
+            request.send_bytes(body)
+        };
+        result.map_err(|e| match e {
+            ureq::Error::Status(404, _) => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found in bucket {}", object_key, self.bucket),
+            ),
+            ureq::Error::Status(code, response) => io::Error::other(format!(
+                "S3 {} {} failed with status {}: {}",
+                method,
+                object_key,
+                code,
+                response.into_string().unwrap_or_default()
+            )),
+            other => io::Error::other(format!("S3 request failed: {}", other)),
+        })
+    }
+
+    /// Headers that authenticate a request, following AWS Signature Version 4.
+    fn sign(
+        &self,
+        method: &str,
+        host: &str,
+        canonical_uri: &str,
+        canonical_query: &str,
+        payload_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<(String, String)> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let mut headers = vec![
+            ("host".to_string(), host.to_string()),
+            ("x-amz-content-sha256".to_string(), payload_hash.to_string()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        headers.sort();
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, canonical_uri, canonical_query, canonical_headers, signed_headers, payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            to_hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let mut key = hmac_sha256(
+            format!("AWS4{}", self.secret_access_key).as_bytes(),
+            date.as_bytes(),
+        );
+        for part in [self.region.as_str(), "s3", "aws4_request"] {
+            key = hmac_sha256(&key, part.as_bytes());
+        }
+        let signature = to_hex(&hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        headers.retain(|(name, _)| name != "host"); // Set by the HTTP client from the URL
+        headers.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        headers
+    }
+
+    fn upload_multipart(
+        &self,
+        object_key: &str,
+        first_part: Vec<u8>,
+        data: &mut dyn Read,
+    ) -> io::Result<u64> {
+        let response = self.send("POST", object_key, &[("uploads", "")], &[])?;
+        let body = response.into_string()?;
+        let upload_id = xml::first_text(&body, "UploadId")
+            .ok_or_else(|| io::Error::other("S3 did not return an upload id"))?;
+
+        let result = self.upload_parts(object_key, &upload_id, first_part, data);
+        if result.is_err() {
+            // Abort so the bucket is not left holding orphaned parts
+            let _ = self.send("DELETE", object_key, &[("uploadId", &upload_id)], &[]);
+        }
+        result
+    }
+
+    fn upload_parts(
+        &self,
+        object_key: &str,
+        upload_id: &str,
+        first_part: Vec<u8>,
+        data: &mut dyn Read,
+    ) -> io::Result<u64> {
+        let mut etags = Vec::new();
+        let mut total = 0u64;
+        let mut part = first_part;
+
+        while !part.is_empty() {
+            let part_number = (etags.len() + 1).to_string();
+            let response = self.send(
+                "PUT",
+                object_key,
+                &[("partNumber", &part_number), ("uploadId", upload_id)],
+                &part,
+            )?;
+            let etag = response
+                .header("ETag")
+                .ok_or_else(|| io::Error::other("S3 did not return an ETag for an uploaded part"))?
+                .to_string();
+            etags.push(etag);
+            total += part.len() as u64;
+            part = read_part(data)?;
+        }
+
+        let parts: String = etags
+            .iter()
+            .enumerate()
+            .map(|(i, etag)| {
+                format!(
+                    "<Part><PartNumber>{}</PartNumber><ETag>{}</ETag></Part>",
+                    i + 1,
+                    etag
+                )
+            })
+            .collect();
+        let body = format!(
+            "<CompleteMultipartUpload>{}</CompleteMultipartUpload>",
+            parts
+        );
+        let response = self.send(
+            "POST",
+            object_key,
+            &[("uploadId", upload_id)],
+            body.as_bytes(),
+        )?;
+
+        // S3 can report a failed completion with a 200 status and an error body
+        let body = response.into_string()?;
+        if body.contains("<Error>") {
+            return Err(io::Error::other(format!(
+                "S3 failed to complete the upload of {}: {}",
+                object_key, body
+            )));
+        }
+        Ok(total)
+    }
+}
+
+impl StorageBackend for S3Backend {
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64> {
+        let object_key = self.object_key(key);
+        let first_part = read_part(data)?;
+        if first_part.len() < PART_SIZE {
+            self.send("PUT", &object_key, &[], &first_part)?;
+            Ok(first_part.len() as u64)
+        } else {
+            self.upload_multipart(&object_key, first_part, data)
+        }
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64> {
+        let response = self.send("GET", &self.object_key(key), &[], &[])?;
+        io::copy(&mut response.into_reader(), out)
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        // Treat the prefix as a folder, like the local backend does
+        let mut list_prefix = self.object_key(prefix);
+        if !list_prefix.is_empty() {
+            list_prefix.push('/');
+        }
+
+        let mut objects = Vec::new();
+        let mut continuation_token: Option<String> = None;
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", list_prefix.as_str())];
+            if let Some(token) = &continuation_token {
+                query.push(("continuation-token", token.as_str()));
+            }
+            let body = self.send("GET", "", &query, &[])?.into_string()?;
+
+            for contents in xml::values(&body, "Contents") {
+                let key = xml::first_text(contents, "Key");
+                let size = xml::first_text(contents, "Size");
+                if let (Some(key), Some(size)) = (key, size) {
+                    let relative = key
+                        .strip_prefix(&self.prefix)
+                        .unwrap_or(&key)
+                        .trim_start_matches('/')
+                        .to_string();
+                    objects.push(ObjectInfo {
+                        key: relative,
+                        size: size.parse().unwrap_or(0),
+                    });
+                }
+            }
+
+            continuation_token = xml::first_text(&body, "NextContinuationToken");
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match self.send("DELETE", &self.object_key(key), &[], &[]) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>> {
+        match self.send("HEAD", &self.object_key(key), &[], &[]) {
+            Ok(response) => Ok(Some(ObjectInfo {
+                key: key.to_string(),
+                size: response
+                    .header("Content-Length")
+                    .and_then(|length| length.parse().ok())
+                    .unwrap_or(0),
+            })),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("s3://{}", join_key(&[&self.bucket, &self.prefix]))
+    }
+}
+
+/// Read up to `PART_SIZE` bytes, stopping early only at the end of the stream.
+fn read_part(data: &mut dyn Read) -> io::Result<Vec<u8>> {
+    let mut part = Vec::with_capacity(PART_SIZE);
+    data.take(PART_SIZE as u64).read_to_end(&mut part)?;
+    Ok(part)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encode everything except RFC 3986 unreserved characters, as SigV4 expects.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
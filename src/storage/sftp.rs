@@ -0,0 +1,289 @@
+use ssh2::{OpenFlags, OpenType, RenameFlags, Session, Sftp};
+use std::{
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::Mutex,
+};
+
+use tracing::warn;
+
+use super::backend::{ObjectInfo, StorageBackend};
+use crate::{config::SftpSettings, sftp};
+
+/// Uploads are sent in chunks of this size. After a dropped connection the upload
+/// resumes from what the server already acknowledged, so at most one chunk is resent.
+/// This only holds within one `put`: a later one starts the file over.
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How many times a dropped upload is resumed before giving up.
+const MAX_RESUMES: usize = 3;
+
+/// Added to the name of a file while it is uploaded. Unusual enough that no world file
+/// ends in it, so `list` can leave out unfinished uploads without hiding real files.
+const PARTIAL_SUFFIX: &str = ".rustcraft-part";
+
+/// Stores backups on a remote host over SFTP, e.g. a NAS that is only reachable over SSH.
+pub struct SftpBackend {
+    settings: SftpSettings,
+    root: String,
+    connection: Mutex<Option<(Session, Sftp)>>,
+}
+
+impl SftpBackend {
+    pub fn new(settings: &SftpSettings) -> io::Result<Self> {
+        if settings.host.is_empty() || settings.remote_path.is_empty() {
+            return Err(io::Error::other(
+                "SFTP host and remote path must be configured",
+            ));
+        }
+        Ok(Self {
+            settings: settings.clone(),
+            root: settings.remote_path.trim_end_matches('/').to_string(),
+            connection: Mutex::new(None),
+        })
+    }
+
+    fn remote_path(&self, key: &str) -> String {
+        let key = key.trim_matches('/');
+        if key.is_empty() {
+            self.root.clone()
+        } else {
+            format!("{}/{}", self.root, key)
+        }
+    }
+
+    /// Run `f` with an open SFTP channel, connecting on first use.
+    fn with_sftp<T>(&self, f: impl FnOnce(&Sftp) -> io::Result<T>) -> io::Result<T> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(sftp::connect(&self.settings)?);
+        }
+        let (_, channel) = connection.as_ref().unwrap();
+        f(channel)
+    }
+
+    /// Drop the current connection, so the next operation opens a fresh one.
+    fn disconnect(&self) {
+        *self.connection.lock().unwrap() = None;
+    }
+
+    fn create_parent_dirs(&self, sftp: &Sftp, remote_path: &str) -> io::Result<()> {
+        let Some((parent, _)) = remote_path.rsplit_once('/') else {
+            return Ok(());
+        };
+        let mut current = String::new();
+        for segment in parent.split('/') {
+            if segment.is_empty() {
+                current.push('/');
+                continue;
+            }
+            if !current.is_empty() && !current.ends_with('/') {
+                current.push('/');
+            }
+            current.push_str(segment);
+            if sftp.stat(Path::new(&current)).is_err() {
+                sftp.mkdir(Path::new(&current), 0o755)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `chunk` at `offset` of the partial upload, resuming after connection drops.
+    fn write_chunk(&self, partial_path: &str, offset: u64, chunk: &[u8]) -> io::Result<()> {
+        let mut attempt = 0;
+        loop {
+            let result = self.with_sftp(|sftp| {
+                // The server may already have part of this chunk from a dropped attempt
+                let remote_size = match sftp.stat(Path::new(partial_path)) {
+                    Ok(stat) => stat.size.unwrap_or(0),
+                    Err(_) => 0,
+                };
+                if remote_size < offset {
+                    return Err(io::Error::other(format!(
+                        "{} lost data on the server, cannot resume",
+                        partial_path
+                    )));
+                }
+                let already_sent = (remote_size - offset).min(chunk.len() as u64);
+                let start = offset + already_sent;
+
+                let mut file = sftp.open_mode(
+                    Path::new(partial_path),
+                    OpenFlags::WRITE | OpenFlags::CREATE,
+                    0o644,
+                    OpenType::File,
+                )?;
+                file.seek(SeekFrom::Start(start))?;
+                file.write_all(&chunk[already_sent as usize..])?;
+                let _ = file.fsync(); // Not every server supports fsync
+                Ok(())
+            });
+
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < MAX_RESUMES => {
+                    attempt += 1;
+                    warn!(
+                        "SFTP upload of {} interrupted ({}), resuming (attempt {} of {})",
+                        partial_path, e, attempt, MAX_RESUMES
+                    );
+                    self.disconnect();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn list_recursively(
+        &self,
+        sftp: &Sftp,
+        remote_dir: &str,
+        objects: &mut Vec<ObjectInfo>,
+    ) -> io::Result<()> {
+        for (path, stat) in sftp.readdir(Path::new(remote_dir))? {
+            // Build remote paths by hand: `Path::join` would use `\` on Windows
+            let Some(name) = path.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let remote_path = format!("{}/{}", remote_dir, name);
+            if stat.is_dir() {
+                self.list_recursively(sftp, &remote_path, objects)?;
+            } else if stat.is_file() && !name.ends_with(PARTIAL_SUFFIX) {
+                objects.push(ObjectInfo {
+                    key: self.key_for(&remote_path),
+                    size: stat.size.unwrap_or(0),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn key_for(&self, remote_path: &str) -> String {
+        remote_path
+            .strip_prefix(&self.root)
+            .unwrap_or(remote_path)
+            .trim_start_matches('/')
+            .to_string()
+    }
+}
+
+impl StorageBackend for SftpBackend {
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64> {
+        let remote_path = self.remote_path(key);
+        let partial_path = format!("{}{}", remote_path, PARTIAL_SUFFIX);
+
+        self.with_sftp(|sftp| {
+            self.create_parent_dirs(sftp, &remote_path)?;
+            // Start from scratch: a leftover partial file belongs to an older attempt, and
+            // the data may have changed since
+            let _ = sftp.unlink(Path::new(&partial_path));
+            Ok(())
+        })?;
+
+        let mut offset = 0u64;
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        loop {
+            chunk.clear();
+            data.take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+            if chunk.is_empty() && offset > 0 {
+                break;
+            }
+            self.write_chunk(&partial_path, offset, &chunk)?;
+            offset += chunk.len() as u64;
+            if chunk.len() < CHUNK_SIZE {
+                break;
+            }
+        }
+
+        // Only give the file its final name once it is complete
+        self.with_sftp(|sftp| {
+            let flags = Some(RenameFlags::OVERWRITE | RenameFlags::ATOMIC | RenameFlags::NATIVE);
+            if sftp
+                .rename(Path::new(&partial_path), Path::new(&remote_path), flags)
+                .is_err()
+            {
+                // Some servers refuse to rename over an existing file
+                let _ = sftp.unlink(Path::new(&remote_path));
+                sftp.rename(Path::new(&partial_path), Path::new(&remote_path), None)?;
+            }
+            Ok(())
+        })?;
+        Ok(offset)
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64> {
+        self.with_sftp(|sftp| {
+            let mut file = sftp.open(Path::new(&self.remote_path(key)))?;
+            io::copy(&mut file, out)
+        })
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        let start = self.remote_path(prefix);
+        let mut objects = self.with_sftp(|sftp| {
+            let mut objects = Vec::new();
+            match sftp.stat(Path::new(&start)) {
+                Ok(stat) if stat.is_dir() => self.list_recursively(sftp, &start, &mut objects)?,
+                Ok(stat) if stat.is_file() => objects.push(ObjectInfo {
+                    key: self.key_for(&start),
+                    size: stat.size.unwrap_or(0),
+                }),
+                _ => {}
+            }
+            Ok(objects)
+        })?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        let remote_path = self.remote_path(key);
+        self.with_sftp(|sftp| {
+            match sftp.unlink(Path::new(&remote_path)) {
+                Ok(()) => {}
+                Err(e) => {
+                    let e = io::Error::from(e);
+                    if e.kind() == io::ErrorKind::NotFound {
+                        return Ok(());
+                    }
+                    return Err(e);
+                }
+            }
+
+            // Clean up directories left empty, but never the root itself.
+            let mut dir = remote_path.as_str();
+            while let Some((parent, _)) = dir.rsplit_once('/') {
+                if parent.len() <= self.root.len() || sftp.rmdir(Path::new(parent)).is_err() {
+                    break; // Reached the root, or not empty
+                }
+                dir = parent;
+            }
+            Ok(())
+        })
+    }
+
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>> {
+        self.with_sftp(|sftp| match sftp.stat(Path::new(&self.remote_path(key))) {
+            Ok(stat) if stat.is_file() => Ok(Some(ObjectInfo {
+                key: key.to_string(),
+                size: stat.size.unwrap_or(0),
+            })),
+            Ok(_) => Ok(None),
+            Err(e) => {
+                let e = io::Error::from(e);
+                if e.kind() == io::ErrorKind::NotFound {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+        })
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "sftp://{}@{}{}",
+            self.settings.username, self.settings.host, self.root
+        )
+    }
+}
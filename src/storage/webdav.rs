@@ -0,0 +1,309 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+    sync::Mutex,
+    time::Duration,
+};
+
+use super::backend::{ObjectInfo, StorageBackend};
+use super::xml;
+use crate::config::WebDavSettings;
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:resourcetype/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+/// Stores backups on a WebDAV server such as Nextcloud or ownCloud.
+pub struct WebDavBackend {
+    agent: ureq::Agent,
+    /// Root URL without a trailing slash, e.g. `https://cloud.example.com/remote.php/dav/files/alex/Minecraft`.
+    base_url: String,
+    /// Decoded path part of `base_url`, used to turn `href`s from PROPFIND back into keys.
+    base_path: String,
+    authorization: Option<String>,
+    /// Collections known to exist, so MKCOL is only sent once per folder.
+    created: Mutex<HashSet<String>>,
+}
+
+/// One entry of a PROPFIND response.
+struct DavEntry {
+    key: String,
+    is_collection: bool,
+    size: u64,
+}
+
+impl WebDavBackend {
+    pub fn new(settings: &WebDavSettings) -> io::Result<Self> {
+        let base_url = settings.url.trim_end_matches('/').to_string();
+        let Some((_, rest)) = base_url.split_once("://") else {
+            return Err(io::Error::other(format!(
+                "Invalid WebDAV URL: {}",
+                settings.url
+            )));
+        };
+        let base_path = rest
+            .find('/')
+            .map(|start| percent_decode(&rest[start..]))
+            .unwrap_or_default();
+
+        let authorization = settings.username.as_ref().map(|username| {
+            let credentials = format!(
+                "{}:{}",
+                username,
+                settings.password.clone().unwrap_or_default()
+            );
+            format!("Basic {}", BASE64.encode(credentials))
+        });
+
+        Ok(Self {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(30))
+                .timeout_read(Duration::from_secs(300))
+                .timeout_write(Duration::from_secs(300))
+                .build(),
+            base_url,
+            base_path: base_path.trim_end_matches('/').to_string(),
+            authorization,
+            created: Mutex::new(HashSet::new()),
+        })
+    }
+
+    fn url_for(&self, key: &str) -> String {
+        let encoded: Vec<String> = key
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_encode)
+            .collect();
+        if encoded.is_empty() {
+            format!("{}/", self.base_url)
+        } else {
+            format!("{}/{}", self.base_url, encoded.join("/"))
+        }
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let request = self.agent.request(method, url);
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    fn map_error(&self, method: &str, key: &str, error: ureq::Error) -> io::Error {
+        match error {
+            ureq::Error::Status(404, _) => io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} not found on {}", key, self.base_url),
+            ),
+            ureq::Error::Status(code, response) => io::Error::other(format!(
+                "WebDAV {} {} failed with status {} {}",
+                method,
+                key,
+                code,
+                response.status_text()
+            )),
+            other => io::Error::other(format!("WebDAV request failed: {}", other)),
+        }
+    }
+
+    /// Create every collection above `key` that does not exist yet.
+    fn create_collections(&self, key: &str) -> io::Result<()> {
+        let segments: Vec<&str> = key.split('/').filter(|s| !s.is_empty()).collect();
+        let mut created = self.created.lock().unwrap();
+        for depth in 1..segments.len() {
+            let folder = segments[..depth].join("/");
+            if created.contains(&folder) {
+                continue;
+            }
+            match self.request("MKCOL", &self.url_for(&folder)).call() {
+                // 405 Method Not Allowed means the collection already exists
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(e) => return Err(self.map_error("MKCOL", &folder, e)),
+            }
+            created.insert(folder);
+        }
+        Ok(())
+    }
+
+    /// Entries of the collection at `key`, including the collection itself.
+    fn propfind(&self, key: &str, depth: &str) -> io::Result<Vec<DavEntry>> {
+        let response = self
+            .request("PROPFIND", &self.url_for(key))
+            .set("Depth", depth)
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY)
+            .map_err(|e| self.map_error("PROPFIND", key, e))?;
+        let body = response.into_string()?;
+
+        Ok(xml::values(&body, "response")
+            .into_iter()
+            .filter_map(|entry| {
+                let href = percent_decode(&xml::first_text(entry, "href")?);
+                // Servers may answer with a full URL or just the path
+                let path = match href.split_once("://") {
+                    Some((_, rest)) => rest.find('/').map(|i| rest[i..].to_string())?,
+                    None => href,
+                };
+                let key = path
+                    .strip_prefix(&self.base_path)?
+                    .trim_matches('/')
+                    .to_string();
+                let is_collection = xml::values(entry, "resourcetype")
+                    .iter()
+                    .any(|resource_type| !xml::values(resource_type, "collection").is_empty());
+                let size = xml::first_text(entry, "getcontentlength")
+                    .and_then(|length| length.trim().parse().ok())
+                    .unwrap_or(0);
+                Some(DavEntry {
+                    key,
+                    is_collection,
+                    size,
+                })
+            })
+            .collect())
+    }
+
+    // Depth: infinity is disabled on most servers, so walk the tree one level at a time.
+    fn list_recursively(&self, key: &str, objects: &mut Vec<ObjectInfo>) -> io::Result<()> {
+        for entry in self.propfind(key, "1")? {
+            if entry.key == key.trim_matches('/') {
+                continue; // The collection itself
+            }
+            if entry.is_collection {
+                self.list_recursively(&entry.key, objects)?;
+            } else {
+                objects.push(ObjectInfo {
+                    key: entry.key,
+                    size: entry.size,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl StorageBackend for WebDavBackend {
+    fn put(&self, key: &str, data: &mut dyn Read) -> io::Result<u64> {
+        self.create_collections(key)?;
+        // Without a Content-Length the body is streamed with chunked transfer encoding,
+        // so large archives never have to fit in memory.
+        let mut reader = CountingReader {
+            inner: data,
+            count: 0,
+        };
+        self.request("PUT", &self.url_for(key))
+            .send(&mut reader)
+            .map_err(|e| self.map_error("PUT", key, e))?;
+        Ok(reader.count)
+    }
+
+    fn get(&self, key: &str, out: &mut dyn Write) -> io::Result<u64> {
+        let response = self
+            .request("GET", &self.url_for(key))
+            .call()
+            .map_err(|e| self.map_error("GET", key, e))?;
+        io::copy(&mut response.into_reader(), out)
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<ObjectInfo>> {
+        let mut objects = Vec::new();
+        match self.list_recursively(prefix, &mut objects) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        }
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match self.request("DELETE", &self.url_for(key)).call() {
+            Ok(_) | Err(ureq::Error::Status(404, _)) => {}
+            Err(e) => return Err(self.map_error("DELETE", key, e)),
+        }
+
+        // Remove collections left empty, so retention does not leave empty folders behind
+        let mut segments: Vec<&str> = key.split('/').filter(|s| !s.is_empty()).collect();
+        while segments.len() > 1 {
+            segments.pop();
+            let folder = segments.join("/");
+            let is_empty = self
+                .propfind(&folder, "1")
+                .map(|entries| entries.len() <= 1)
+                .unwrap_or(false);
+            if !is_empty {
+                break;
+            }
+            if let Err(e) = self.request("DELETE", &self.url_for(&folder)).call() {
+                return Err(self.map_error("DELETE", &folder, e));
+            }
+            self.created.lock().unwrap().remove(&folder);
+        }
+        Ok(())
+    }
+
+    fn stat(&self, key: &str) -> io::Result<Option<ObjectInfo>> {
+        match self.propfind(key, "0") {
+            Ok(entries) => {
+                Ok(entries
+                    .into_iter()
+                    .find(|entry| !entry.is_collection)
+                    .map(|entry| ObjectInfo {
+                        key: key.to_string(),
+                        size: entry.size,
+                    }))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.base_url.clone()
+    }
+}
+
+struct CountingReader<'a> {
+    inner: &'a mut dyn Read,
+    count: u64,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count += read as u64;
+        Ok(read)
+    }
+}
+
+fn percent_encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
@@ -0,0 +1,51 @@
+// Just enough XML reading for the responses of S3 and WebDAV servers, which are
+// simple enough that a full XML parser is not worth the dependency.
+
+/// Raw contents of every element called `name` in `xml`, ignoring any namespace
+/// prefix, so `values(xml, "href")` finds `<href>`, `<d:href>` and `<D:href>`.
+/// Self-closing elements yield an empty string.
+pub fn values<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[..tag_end];
+        let tag_name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("");
+        let local_name = tag_name.rsplit(':').next().unwrap_or(tag_name);
+        rest = &rest[tag_end + 1..];
+
+        if local_name != name || tag.starts_with('/') || tag.starts_with('?') {
+            continue;
+        }
+        if tag.ends_with('/') {
+            found.push("");
+            continue;
+        }
+        let close = format!("</{}>", tag_name);
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        found.push(&rest[..end]);
+        rest = &rest[end + close.len()..];
+    }
+    found
+}
+
+/// Text of the first element called `name`, with entities decoded.
+pub fn first_text(xml: &str, name: &str) -> Option<String> {
+    values(xml, name).first().map(|value| unescape(value))
+}
+
+pub fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
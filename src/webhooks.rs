@@ -0,0 +1,212 @@
+use serde_json::json;
+use std::{io, thread, time::Duration};
+use tracing::{info, warn};
+
+use crate::config::{WebhookFormat, WebhookSettings};
+use crate::runs::{self, RunRecord};
+
+/// Attempts per message. Waits double after each failed one, starting at a second.
+const ATTEMPTS: u32 = 4;
+
+/// What happened to a backup run, as far as webhooks are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Success,
+    Failure,
+    Skipped,
+}
+
+impl Event {
+    pub fn of(run: &RunRecord) -> Self {
+        match run.error {
+            None => Event::Success,
+            Some(_) if run.skipped => Event::Skipped,
+            Some(_) => Event::Failure,
+        }
+    }
+
+    /// `success`, `failure` or `skipped`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::Success => "success",
+            Event::Failure => "failure",
+            Event::Skipped => "skipped",
+        }
+    }
+}
+
+/// One line describing a run, the message chat webhooks post.
+pub fn message(run: &RunRecord) -> String {
+    let worlds = if run.worlds.is_empty() {
+        run.source.clone()
+    } else {
+        run.worlds.join(", ")
+    };
+    let error = run.error.as_deref().unwrap_or_default();
+    match Event::of(run) {
+        Event::Success => format!(
+            "Backed up {}: {} in {}s ({})",
+            worlds,
+            runs::format_size(run.bytes),
+            (run.finished - run.started).num_seconds(),
+            run.snapshot_id.as_deref().unwrap_or_default()
+        ),
+        Event::Failure => format!("Backup of {} failed: {}", worlds, error),
+        Event::Skipped => format!("Backup of {} skipped: {}", worlds, error),
+    }
+}
+
+/// Post a run to every webhook that wants to hear about its outcome. Runs in the background,
+/// so a slow or unreachable server never holds up the next backup.
+pub fn send_all(webhooks: &[WebhookSettings], run: &RunRecord) {
+    let event = Event::of(run);
+    for webhook in webhooks {
+        let muted = match event {
+            Event::Success => webhook.mute_success,
+            Event::Failure => webhook.mute_failure,
+            Event::Skipped => webhook.mute_skipped,
+        };
+        if muted || webhook.url.is_empty() {
+            continue;
+        }
+        let webhook = webhook.clone();
+        let run = run.clone();
+        thread::spawn(move || {
+            if let Err(e) = send(&webhook, &run) {
+                warn!("{}", e);
+            }
+        });
+    }
+}
+
+/// Post a run to one webhook, retrying on network errors and server-side failures.
+pub fn send(webhook: &WebhookSettings, run: &RunRecord) -> io::Result<()> {
+    send_with_attempts(webhook, run, ATTEMPTS)
+}
+
+fn send_with_attempts(webhook: &WebhookSettings, run: &RunRecord, attempts: u32) -> io::Result<()> {
+    let body = payload(webhook, run)?;
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(30))
+        .build();
+
+    let mut delay = Duration::from_secs(1);
+    for attempt in 1..=attempts {
+        let result = agent
+            .post(&webhook.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+        let error = match result {
+            Ok(_) => {
+                info!(
+                    "Sent the {} webhook to {}",
+                    Event::of(run).name(),
+                    host(&webhook.url)
+                );
+                return Ok(());
+            }
+            // Anything else in 4xx means the webhook itself is wrong; trying again will not help
+            Err(ureq::Error::Status(code, response)) if code != 429 && code < 500 => {
+                return Err(io::Error::other(format!(
+                    "Webhook {} refused the message with status {} {}",
+                    host(&webhook.url),
+                    code,
+                    response.status_text()
+                )));
+            }
+            Err(e) => e,
+        };
+        if attempt == attempts {
+            return Err(io::Error::other(format!(
+                "Webhook {} failed after {} attempts: {}",
+                host(&webhook.url),
+                attempts,
+                error
+            )));
+        }
+        warn!(
+            "Webhook {} failed ({}), retrying in {}s",
+            host(&webhook.url),
+            error,
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+        delay *= 2;
+    }
+    Ok(())
+}
+
+/// Post a made-up successful run, to check a webhook is set up right. Tried only once,
+/// since someone is waiting for the answer.
+pub fn send_test(webhook: &WebhookSettings) -> io::Result<()> {
+    let mut run = RunRecord::new(runs::Trigger::Manual, "RustCraft".to_string());
+    run.worlds = vec!["a test world".to_string()];
+    run.snapshot_id = Some("this is a test".to_string());
+    send_with_attempts(webhook, &run, 1)
+}
+
+/// Only the host is logged; the path of a Discord or Slack webhook is its password.
+fn host(url: &str) -> &str {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    without_scheme.split('/').next().unwrap_or_default()
+}
+
+fn payload(webhook: &WebhookSettings, run: &RunRecord) -> io::Result<String> {
+    let message = message(run);
+    let body = match webhook.format {
+        WebhookFormat::Discord => json!({ "username": "RustCraft", "content": message }),
+        WebhookFormat::Slack => json!({ "text": message }),
+        WebhookFormat::Json => match &webhook.template {
+            Some(template) => return Ok(render_template(template, run, &message)),
+            None => json!({ "event": Event::of(run).name(), "message": message, "run": run }),
+        },
+    };
+    Ok(body.to_string())
+}
+
+/// Fill `{{name}}` placeholders in a JSON template. Values are JSON strings, quotes
+/// included, so `{"text": {{message}}}` stays valid whatever the message holds.
+fn render_template(template: &str, run: &RunRecord, message: &str) -> String {
+    let fields = [
+        ("event", Event::of(run).name().to_string()),
+        ("message", message.to_string()),
+        ("source", run.source.clone()),
+        ("destination", run.destination.clone()),
+        ("worlds", run.worlds.join(", ")),
+        ("snapshot_id", run.snapshot_id.clone().unwrap_or_default()),
+        ("size", runs::format_size(run.bytes)),
+        ("files", run.files.to_string()),
+        (
+            "duration",
+            (run.finished - run.started).num_seconds().to_string(),
+        ),
+        ("error", run.error.clone().unwrap_or_default()),
+        ("started", run.started.to_rfc3339()),
+        ("finished", run.finished.to_rfc3339()),
+    ];
+
+    // One pass over the template, so placeholders inside the values are left alone
+    let mut body = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        body.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let field = after.find("}}").and_then(|end| {
+            let (_, value) = fields.iter().find(|(name, _)| *name == &after[..end])?;
+            Some((end, value))
+        });
+        match field {
+            Some((end, value)) => {
+                body.push_str(&serde_json::Value::String(value.clone()).to_string());
+                rest = &after[end + 2..];
+            }
+            // Not a placeholder we know, so it stays as written
+            None => {
+                body.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    body.push_str(rest);
+    body
+}